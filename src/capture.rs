@@ -0,0 +1,682 @@
+//! Layout of the capture files and the scaling of their raw values, usable without `std` for parsing on devices
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{Binary, Debug, Display, Formatter, LowerHex, UpperHex};
+use core::str::FromStr;
+use binread::{BinRead, BinReaderExt, io::{Cursor, SeekFrom}};
+use serde::{Serialize, Serializer};
+use num_enum::{TryFromPrimitive, TryFromPrimitiveError};
+
+pub const DIVISION_POINTS: f32 = 50.0;
+pub const VOLTAGE_MEASUREMENT_DIVISOR: f32 = 1024f32;
+/// Vertical divisions on screen, half of them above and half below 0V
+pub const VERTICAL_DIVISIONS: f32 = 8.0;
+/// Largest count of the 10 bit ADC both the samples and the channel offsets are expressed in
+pub const ADC_MAX: u16 = 1023;
+/// Channel offset of a trace in the middle of the screen
+pub const ADC_MIDPOINT: u16 = 512;
+pub const CHANNEL_SAMPLES: usize = 1500;
+pub const ZOOM_SAMPLES: usize = 750;
+pub const CHANNEL1_MEASUREMENTS_OFFSET: u64 = 208;
+pub const CHANNEL2_MEASUREMENTS_OFFSET: u64 = 256;
+pub const CHANNEL_DATA_OFFSET: u64 = 1000;
+
+/// This is an example for using doc comment attributes
+pub static TIME_SCALES: [ScaleEntry<Second>; 33] = [
+    ScaleEntry::valid(Scale { value: 50.0, scale: 0, unit: Second }),
+    ScaleEntry::valid(Scale { value: 20.0, scale: 0, unit: Second }),
+    ScaleEntry::valid(Scale { value: 10.0, scale: 0, unit: Second }),
+    ScaleEntry::valid(Scale { value: 5.0, scale: 0, unit: Second }),
+    ScaleEntry::valid(Scale { value: 2.0, scale: 0, unit: Second }),
+    ScaleEntry::valid(Scale { value: 1.0, scale: 0, unit: Second }),
+    ScaleEntry::valid(Scale { value: 500.0, scale: -3, unit: Second }),
+    ScaleEntry::valid(Scale { value: 200.0, scale: -3, unit: Second }),
+    ScaleEntry::valid(Scale { value: 100.0, scale: -3, unit: Second }),
+    ScaleEntry::valid(Scale { value: 50.0, scale: -3, unit: Second }),
+    ScaleEntry::valid(Scale { value: 20.0, scale: -3, unit: Second }),
+    ScaleEntry::valid(Scale { value: 10.0, scale: -3, unit: Second }),
+    ScaleEntry::valid(Scale { value: 5.0, scale: -3, unit: Second }),
+    ScaleEntry::valid(Scale { value: 2.0, scale: -3, unit: Second }),
+    ScaleEntry::valid(Scale { value: 1.0, scale: -3, unit: Second }),
+    ScaleEntry::valid(Scale { value: 500.0, scale: -6, unit: Second }),
+    ScaleEntry::valid(Scale { value: 200.0, scale: -6, unit: Second }),
+    ScaleEntry::valid(Scale { value: 100.0, scale: -6, unit: Second }),
+    ScaleEntry::valid(Scale { value: 50.0, scale: -6, unit: Second }),
+    ScaleEntry::valid(Scale { value: 20.0, scale: -6, unit: Second }),
+    ScaleEntry::valid(Scale { value: 10.0, scale: -6, unit: Second }),
+    ScaleEntry::valid(Scale { value: 5.0, scale: -6, unit: Second }),
+    ScaleEntry::valid(Scale { value: 2.0, scale: -6, unit: Second }),
+    ScaleEntry::valid(Scale { value: 1.0, scale: -6, unit: Second }),
+    ScaleEntry::valid(Scale { value: 500.0, scale: -9, unit: Second }),
+    ScaleEntry::valid(Scale { value: 200.0, scale: -9, unit: Second }),
+    ScaleEntry::valid(Scale { value: 100.0, scale: -9, unit: Second }),
+    ScaleEntry::valid(Scale { value: 50.0, scale: -9, unit: Second }),
+    ScaleEntry::valid(Scale { value: 20.0, scale: -9, unit: Second }),
+    ScaleEntry::valid(Scale { value: 10.0, scale: -9, unit: Second }),
+    ScaleEntry::valid(Scale { value: 5.0, scale: -9, unit: Second }),
+    ScaleEntry::valid(Scale { value: 2.0, scale: -9, unit: Second }),
+    ScaleEntry::valid(Scale { value: 1.0, scale: -9, unit: Second }),
+];
+
+/// This is an example for using doc comment attributes
+pub static PROBE_SCALES: [ScaleEntry<Volt>; 7] = [
+    ScaleEntry::valid(Scale { value: 5.0, scale: 0, unit: Volt }),
+    ScaleEntry::valid(Scale { value: 2.5, scale: 0, unit: Volt }),
+    ScaleEntry::valid(Scale { value: 1.0, scale: 0, unit: Volt }),
+    ScaleEntry::valid(Scale { value: 500.0, scale: -3, unit: Volt }),
+    ScaleEntry::valid(Scale { value: 200.0, scale: -3, unit: Volt }),
+    ScaleEntry::valid(Scale { value: 100.0, scale: -3, unit: Volt }),
+    ScaleEntry::valid(Scale { value: 50.0, scale: -3, unit: Volt }),
+];
+
+/// Scope models whose captures can be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FnirsiModel {
+    /// The only layout known so far
+    Fnirsi1013D
+}
+
+/// Sample counts and offsets of the captures of a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelLayout {
+    pub channel_samples: usize,
+    pub zoom_samples: usize,
+    /// Divisions of the screen grid the full scale voltage spans
+    pub vertical_divisions: usize,
+    pub channel1_measurements_offset: u64,
+    pub channel2_measurements_offset: u64,
+    pub channel_data_offset: u64
+}
+
+impl FnirsiModel {
+    pub fn layout(&self) -> ModelLayout {
+        match self {
+            FnirsiModel::Fnirsi1013D => ModelLayout {
+                channel_samples: CHANNEL_SAMPLES,
+                zoom_samples: ZOOM_SAMPLES,
+                vertical_divisions: VERTICAL_DIVISIONS as usize,
+                channel1_measurements_offset: CHANNEL1_MEASUREMENTS_OFFSET,
+                channel2_measurements_offset: CHANNEL2_MEASUREMENTS_OFFSET,
+                channel_data_offset: CHANNEL_DATA_OFFSET
+            }
+        }
+    }
+}
+
+/// Sample buffers a capture holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ChannelLayout {
+    /// The main and zoom buffers of channel 1 and 2
+    TwoChannel,
+    /// The buffers of channel 3 and 4 in the same order after the ones of channel 1 and 2.
+    /// No four channel capture has been reverse engineered yet, the order is a guess
+    FourChannel
+}
+
+impl ChannelLayout {
+    /// Four channels when the capture is long enough to hold their buffers, two otherwise
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.len() >= ChannelLayout::FourChannel.file_size() {
+            ChannelLayout::FourChannel
+        } else {
+            ChannelLayout::TwoChannel
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        match self {
+            ChannelLayout::TwoChannel => 2,
+            ChannelLayout::FourChannel => 4
+        }
+    }
+
+    /// Bytes up to the end of the last sample buffer
+    pub fn file_size(&self) -> usize {
+        CHANNEL_DATA_OFFSET as usize + self.channels() * (CHANNEL_SAMPLES + ZOOM_SAMPLES) * core::mem::size_of::<u16>()
+    }
+}
+
+/// Parses a whole capture from its bytes, with the `ChannelLayout` detected from its size
+pub fn parse_file(bytes: &[u8]) -> Result<File, binread::Error> {
+    parse_file_with_layout(bytes, ChannelLayout::detect(bytes))
+}
+
+/// Parses a whole capture from its bytes with the given layout instead of the detected one
+pub fn parse_file_with_layout(bytes: &[u8], layout: ChannelLayout) -> Result<File, binread::Error> {
+    if layout == ChannelLayout::FourChannel && bytes.len() < layout.file_size() {
+        return Err(binread::Error::AssertFail {
+            pos: bytes.len() as u64,
+            message: alloc::format!("the capture has {} bytes, a four channel capture has {}, it is likely a two channel one", bytes.len(), layout.file_size())
+        });
+    }
+
+    Cursor::new(bytes).read_le_args((layout == ChannelLayout::FourChannel,))
+}
+
+pub fn parse_frequency(high: u16, low: u16) -> u32 {
+    ((high as u32) << 16) + low as u32
+}
+
+/// Order of the two words of the frequency in the measurements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordOrder {
+    /// `frequency_high` holds the most significant word, the order `parse_frequency` assumes
+    HighLow,
+    /// `frequency_low` holds the most significant word
+    LowHigh
+}
+
+/// `parse_frequency` with the words in `order`, for firmwares storing them swapped
+pub fn parse_frequency_ordered(high: u16, low: u16, order: WordOrder) -> u32 {
+    match order {
+        WordOrder::HighLow => parse_frequency(high, low),
+        WordOrder::LowHigh => parse_frequency(low, high)
+    }
+}
+
+/// Converts raw samples to points, `offset` is the ADC count of 0V which sits at `ADC_MIDPOINT` for a centered trace.
+/// The offset and the samples have to share the ADC domain for the subtraction to result in a voltage.
+/// Values beyond `ADC_MAX` from a corrupt capture are converted all the same, `parse_data` warns about them.
+pub fn generate_points(values: &[u16], voltage_scale: &Scale<Volt>, time_scale: &Scale<Second>, offset: u16) -> Vec<Point> {
+    values.iter().enumerate().map(| (index, voltage)| Point {
+        time: (index as f32) * time_scale.get_scale()/ DIVISION_POINTS,
+        voltage: (*voltage as f32 - offset as f32) * voltage_scale.get_scale()/DIVISION_POINTS
+    }).collect()
+}
+
+/// Rotates the circular buffer of a roll mode capture, holding its newest sample at `write_pointer`, so the oldest sample comes first.
+/// Write pointers past the end wrap around the buffer.
+pub fn linearize_roll_buffer(values: &[u16], write_pointer: usize) -> Vec<u16> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+
+    let oldest = (write_pointer + 1) % values.len();
+    values[oldest..].iter().chain(&values[..oldest]).copied().collect()
+}
+
+pub fn process_voltage_measurement(measurement: u16) -> f32 {
+    (measurement as f32)/VOLTAGE_MEASUREMENT_DIVISOR
+}
+
+/// How the value of a `Measurements` field is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeasurementEncoding {
+    /// The value itself
+    RawU16,
+    /// Volts times `VOLTAGE_MEASUREMENT_DIVISOR`
+    DividedBy1024,
+    /// Volts times `VOLTAGE_MEASUREMENT_DIVISOR` stored as an `i16`
+    SignedDividedBy1024,
+    /// Not reverse engineered yet, the value is output raw
+    Unknown
+}
+
+/// Known encoding of every `Measurements` field, extend it as more of the format gets reverse engineered.
+/// The minimum and average could turn out to be signed but no capture with negative ones has been seen yet
+pub fn measurement_encoding(field_name: &str) -> MeasurementEncoding {
+    match field_name {
+        "vmax" | "vmin" | "vavg" | "vrms" | "vpp" | "vp" => MeasurementEncoding::DividedBy1024,
+        "frequency_high" | "frequency_low" | "duty_plus_percentage" | "duty_minus_percentage" => MeasurementEncoding::RawU16,
+        // cycle_ns, time_plus_ns and time_minus_ns overflow for anything slower than 15 kHz if they really were nanoseconds
+        _ => MeasurementEncoding::Unknown
+    }
+}
+
+/// Period in seconds of a `cycle_ns`, `time_plus_ns` or `time_minus_ns` value.
+/// Taken as the nanoseconds the fields are named after until captures of known frequencies pin the encoding down,
+/// which is why `measurement_encoding` still reports them as unknown
+pub fn decode_cycle_time(raw_cycle: u16) -> f64 {
+    raw_cycle as f64 * 1e-9
+}
+
+/// Value of a `Measurements` field according to `measurement_encoding`, `None` when its encoding is unknown
+pub fn decode_measurement(field_name: &str, raw: u16) -> Option<f32> {
+    match measurement_encoding(field_name) {
+        MeasurementEncoding::RawU16 => Some(raw as f32),
+        MeasurementEncoding::DividedBy1024 => Some(process_voltage_measurement(raw)),
+        MeasurementEncoding::SignedDividedBy1024 => Some(raw as i16 as f32 / VOLTAGE_MEASUREMENT_DIVISOR),
+        MeasurementEncoding::Unknown => None
+    }
+}
+
+pub fn sample_rate_hz(time_scale: &Scale<Second>) -> f32 {
+    DIVISION_POINTS / time_scale.get_scale()
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Point {
+    pub time: f32,
+    pub voltage: f32
+}
+
+#[derive(BinRead, Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[br(little, import(four_channels: bool))]
+pub struct File {
+    pub header: Header,
+    #[br(count = CHANNEL_SAMPLES, seek_before = SeekFrom::Start(CHANNEL_DATA_OFFSET))]
+    pub channel11: Vec<u16>,
+    #[br(count = CHANNEL_SAMPLES)]
+    pub channel21: Vec<u16>,
+    #[br(count = ZOOM_SAMPLES)]
+    pub channel12: Vec<u16>,
+    #[br(count = ZOOM_SAMPLES)]
+    pub channel22: Vec<u16>,
+    /// The buffers of channel 3 and 4 are empty in two channel captures
+    #[br(count = if four_channels { CHANNEL_SAMPLES } else { 0 })]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub channel31: Vec<u16>,
+    #[br(count = if four_channels { CHANNEL_SAMPLES } else { 0 })]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub channel41: Vec<u16>,
+    #[br(count = if four_channels { ZOOM_SAMPLES } else { 0 })]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub channel32: Vec<u16>,
+    #[br(count = if four_channels { ZOOM_SAMPLES } else { 0 })]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub channel42: Vec<u16>
+}
+
+#[derive(BinRead, Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Header {
+    #[br(pad_before = 4)]
+    pub channel1_scale: u16,
+    #[br(pad_before = 2)]
+    pub channel1_coupling: u16,
+    pub channel1_probe: u16,
+    #[br(pad_before = 2)]
+    pub channel2_scale: u16,
+    #[br(pad_before = 2)]
+    pub channel2_coupling: u16,
+    pub channel2_probe: u16,
+    pub time_scale: u16,
+    pub scroll_speed: u16,
+    pub trigger_type: u16,
+    pub trigger_edge: u16,
+    pub trigger_channel: u16,
+    #[br(pad_before = 52)]
+    pub channel1_offset: u16,
+    pub channel2_offset: u16,
+    #[br(pad_before = 32)]
+    pub screen_brightness: u16,
+    pub grid_brightness: u16,
+    pub trigger_50: u16,
+    #[br(seek_before = SeekFrom::Start(CHANNEL1_MEASUREMENTS_OFFSET))]
+    pub channel1_measurements: Measurements,
+    #[br(seek_before = SeekFrom::Start(CHANNEL2_MEASUREMENTS_OFFSET))]
+    pub channel2_measurements: Measurements
+}
+
+#[derive(BinRead, Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[br(little)]
+pub struct Measurements {
+    #[br(pad_before = 2)]
+    pub vmax: u16,
+    #[br(pad_before = 2)]
+    pub vmin: u16,
+    #[br(pad_before = 2)]
+    pub vavg: u16,
+    #[br(pad_before = 2)]
+    pub vrms: u16,
+    #[br(pad_before = 2)]
+    pub vpp: u16,
+    #[br(pad_before = 2)]
+    pub vp: u16,
+    pub frequency_high: u16,
+    pub frequency_low: u16,
+    #[br(pad_before = 2)]
+    pub cycle_ns: u16,
+    #[br(pad_before = 2)]
+    pub time_plus_ns: u16,
+    #[br(pad_before = 2)]
+    pub time_minus_ns: u16,
+    #[br(pad_before = 2)]
+    pub duty_plus_percentage: u16,
+    #[br(pad_before = 2)]
+    pub duty_minus_percentage: u16
+}
+
+pub trait Unit: Display + Clone + Copy {}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Volt;
+
+impl Unit for Volt {}
+
+impl Serialize for Volt {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        str::serialize("Volt", serializer)
+    }
+}
+
+impl Display for Volt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "V")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Second;
+
+impl Unit for Second {}
+
+impl Serialize for Second {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        str::serialize("Second", serializer)
+    }
+}
+
+impl Display for Second {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "s")
+    }
+}
+
+#[derive(Clone, Serialize, Copy)]
+pub struct Scale<T: Unit> {
+    pub value: f32,
+    pub scale: i32,
+    pub unit: T
+}
+
+impl <T: Unit> Scale<T> {
+    pub fn get_scale(&self) -> f32 {
+        // `f32::powi` needs std, multiplying the power of ten out gives the same result
+        let power = (0..self.scale.unsigned_abs()).fold(1f32, |power, _| power * 10.0);
+        self.value * if self.scale < 0 { 1.0 / power } else { power }
+    }
+}
+
+impl Scale<Volt> {
+    /// Volts per division at the probe tip, `PROBE_SCALES` holds the ones at the scope input so a 100x probe
+    /// on the 5V scale makes 500V. The value stays below 1000 of its prefix, `500mV` times 10 is `5V`
+    pub fn with_attenuation(&self, attenuation: Attenuation) -> Scale<Volt> {
+        let mut scale = Scale { value: self.value * attenuation.multiplier(), ..*self };
+
+        while scale.value >= 1000.0 && scale.scale < 0 {
+            scale.value /= 1000.0;
+            scale.scale += 3;
+        }
+
+        scale
+    }
+}
+
+impl <T: Unit> Display for Scale<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}{}{}", self.value, match self.scale {
+            0 => "",
+            -3 => "m",
+            -6 => "u",
+            -9 => "n",
+            other => unreachable!("Unexpected scale {}", other)
+        }, self.unit)
+    }
+}
+
+/// Compares the bits of the values so scales are `Eq`, a NaN scale equals itself and `-0V` isn't `0V`
+impl <T: Unit> PartialEq for Scale<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.to_bits() == other.value.to_bits() && self.scale == other.scale
+    }
+}
+
+impl <T: Unit> Eq for Scale<T> {}
+
+impl <T: Unit> Debug for Scale<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Scale {{ value {}, scale: {}, string: {} }}", self.value, self.scale, self)
+    }
+}
+
+/// Slot of a scale table
+#[derive(Clone, Copy, Debug)]
+pub struct ScaleEntry<T: Unit> {
+    pub scale: Scale<T>,
+    /// False for placeholders keeping the indices of slots that haven't been reverse engineered yet
+    pub is_valid: bool
+}
+
+impl <T: Unit> ScaleEntry<T> {
+    pub const fn valid(scale: Scale<T>) -> Self {
+        ScaleEntry { scale, is_valid: true }
+    }
+
+    pub const fn placeholder(scale: Scale<T>) -> Self {
+        ScaleEntry { scale, is_valid: false }
+    }
+}
+
+impl <T: Unit> From<Scale<T>> for ScaleEntry<T> {
+    fn from(scale: Scale<T>) -> Self {
+        ScaleEntry::valid(scale)
+    }
+}
+
+/// Looks up a scale index, warning when it points at a placeholder whose scale is only a guess
+pub fn lookup_scale<T: Unit>(table: &[ScaleEntry<T>], number: u16, name: &str) -> Option<Scale<T>> {
+    let entry = table.get(number as usize)?;
+
+    if !entry.is_valid {
+        log::warn!("{} index {} is a placeholder, the scale {} is a guess", name, number, entry.scale);
+    }
+
+    Some(entry.scale)
+}
+
+pub fn probe_scale_to_index(scale: &Scale<Volt>) -> Option<u16> {
+    PROBE_SCALES.iter().position(|candidate| candidate.is_valid && &candidate.scale == scale).map(|index| index as u16)
+}
+
+pub fn time_scale_to_index(scale: &Scale<Second>) -> Option<u16> {
+    TIME_SCALES.iter().position(|candidate| candidate.is_valid && &candidate.scale == scale).map(|index| index as u16)
+}
+
+/// Formats the table index of a scale with `format`, or `??` when the scale isn't in its table
+fn fmt_scale_index(index: Option<u16>, f: &mut Formatter<'_>, format: fn(&u16, &mut Formatter<'_>) -> core::fmt::Result) -> core::fmt::Result {
+    match index {
+        Some(index) => format(&index, f),
+        None => f.pad("??")
+    }
+}
+
+impl LowerHex for Scale<Volt> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        fmt_scale_index(probe_scale_to_index(self), f, LowerHex::fmt)
+    }
+}
+
+impl UpperHex for Scale<Volt> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        fmt_scale_index(probe_scale_to_index(self), f, UpperHex::fmt)
+    }
+}
+
+/// All 16 bits of the table index
+impl Binary for Scale<Volt> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        fmt_scale_index(probe_scale_to_index(self), f, |index, f| write!(f, "{:016b}", index))
+    }
+}
+
+impl LowerHex for Scale<Second> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        fmt_scale_index(time_scale_to_index(self), f, LowerHex::fmt)
+    }
+}
+
+impl UpperHex for Scale<Second> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        fmt_scale_index(time_scale_to_index(self), f, UpperHex::fmt)
+    }
+}
+
+/// All 16 bits of the table index
+impl Binary for Scale<Second> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        fmt_scale_index(time_scale_to_index(self), f, |index, f| write!(f, "{:016b}", index))
+    }
+}
+
+impl TryFromPrimitive for Scale<Volt> {
+    type Primitive = u16;
+    const NAME: &'static str = "Scale<Volt>";
+
+    fn try_from_primitive(number: Self::Primitive) -> Result<Self, TryFromPrimitiveError<Self>> {
+        lookup_scale(&PROBE_SCALES, number, Self::NAME).ok_or(TryFromPrimitiveError { number })
+    }
+}
+
+impl TryFrom<u16> for Scale<Volt> {
+    type Error = TryFromPrimitiveError<Self>;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        TryFromPrimitive::try_from_primitive(value)
+    }
+}
+
+impl TryFromPrimitive for Scale<Second> {
+    type Primitive = u16;
+    const NAME: &'static str = "Scale<Second>";
+
+    fn try_from_primitive(number: Self::Primitive) -> Result<Self, TryFromPrimitiveError<Self>> {
+        lookup_scale(&TIME_SCALES, number, Self::NAME).ok_or(TryFromPrimitiveError { number })
+    }
+}
+
+impl TryFrom<u16> for Scale<Second> {
+    type Error = TryFromPrimitiveError<Self>;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        TryFromPrimitive::try_from_primitive(value)
+    }
+}
+
+/// A string naming none of the variants of an enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidEnumValue {
+    pub value: String,
+    pub enum_name: &'static str
+}
+
+impl Display for InvalidEnumValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "`{}` is not a {}", self.value, self.enum_name)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidEnumValue {}
+
+/// Finds the variant whose name matches `value` ignoring the case
+fn parse_variant<T: Copy>(value: &str, enum_name: &'static str, variants: &[(&str, T)]) -> Result<T, InvalidEnumValue> {
+    variants.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, variant)| *variant)
+        .ok_or_else(|| InvalidEnumValue { value: value.into(), enum_name })
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, TryFromPrimitive, Serialize)]
+#[repr(u16)]
+pub enum Coupling {
+    DC = 0, AC
+}
+
+impl Display for Coupling {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Coupling {
+    type Err = InvalidEnumValue;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse_variant(value, "Coupling", &[("dc", Coupling::DC), ("ac", Coupling::AC)])
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, TryFromPrimitive, Serialize)]
+#[repr(u16)]
+pub enum Attenuation {
+    OneX = 0,
+    TenX,
+    OneHundredX
+}
+
+impl Attenuation {
+    pub fn multiplier(&self) -> f32 {
+        match self {
+            Attenuation::OneX => 1.0,
+            Attenuation::TenX => 10.0,
+            Attenuation::OneHundredX => 100.0
+        }
+    }
+}
+
+impl Display for Attenuation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}×", self.multiplier())
+    }
+}
+
+/// Takes `10x` as well as the `10×` of `Display`
+impl FromStr for Attenuation {
+    type Err = InvalidEnumValue;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse_variant(value, "Attenuation", &[
+            ("1x", Attenuation::OneX), ("1×", Attenuation::OneX),
+            ("10x", Attenuation::TenX), ("10×", Attenuation::TenX),
+            ("100x", Attenuation::OneHundredX), ("100×", Attenuation::OneHundredX)
+        ])
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
+#[repr(u16)]
+pub enum ScrollSpeed {
+    Fast = 0, Slow
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, TryFromPrimitive, Serialize)]
+#[repr(u16)]
+pub enum TriggerType {
+    Auto = 0, Single, Normal
+}
+
+impl FromStr for TriggerType {
+    type Err = InvalidEnumValue;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse_variant(value, "TriggerType", &[("auto", TriggerType::Auto), ("single", TriggerType::Single), ("normal", TriggerType::Normal)])
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, TryFromPrimitive, Serialize)]
+#[repr(u16)]
+pub enum TriggerEdge {
+    Rising = 0, Falling
+}
+
+impl FromStr for TriggerEdge {
+    type Err = InvalidEnumValue;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse_variant(value, "TriggerEdge", &[("rising", TriggerEdge::Rising), ("falling", TriggerEdge::Falling)])
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
+#[repr(u16)]
+pub enum TriggerChannel {
+    Channel1 = 0, Channel2
+}
+
+#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
+#[repr(u16)]
+pub enum Trigger50 {
+    On = 0, Off
+}
@@ -0,0 +1,40 @@
+//! Parses the captures of the FNIRSI 1013D oscilloscope and analyses their waveforms.
+//!
+//! [`parse_file`] reads a capture into a [`File`] and, with the default `std` feature, [`parse_data`] or
+//! [`parse_capture`] turn it into [`Data`] holding the points and measurements of both channels.
+//!
+//! # Custom output formats
+//!
+//! Besides the built-in writers, `Data` can be written in any format by implementing [`OutputFormatter`]
+//! and wrapping it in [`OutputFormat::Custom`], without changes to this crate:
+//!
+//! ```
+//! # #[cfg(feature = "std")] {
+//! use std::io::Write;
+//! use fnirsi::{Data, FnirsiError, OutputFormat, OutputFormatter};
+//!
+//! struct Vpp;
+//!
+//! impl OutputFormatter for Vpp {
+//!     fn write(&self, data: &Data, writer: &mut dyn Write) -> Result<(), FnirsiError> {
+//!         Ok(writeln!(writer, "{} {}", data.channel1.measurements.vpp, data.channel2.measurements.vpp)?)
+//!     }
+//! }
+//!
+//! fn write_vpp(data: &Data) -> Result<(), FnirsiError> {
+//!     OutputFormat::Custom(Box::new(Vpp)).write(data, &mut std::io::stdout())
+//! }
+//! # }
+//! ```
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod capture;
+#[cfg(feature = "std")]
+mod processing;
+
+pub use capture::*;
+#[cfg(feature = "std")]
+pub use processing::*;
@@ -1,75 +1,233 @@
-use std::convert::{TryFrom, TryInto};
-use std::fmt::{Debug, Display, Formatter};
-use binread::{BinRead, BinReaderExt, io::SeekFrom};
+use std::collections::BTreeMap;
 use std::fs::File as FsFile;
-use std::io::stdout;
+use std::io::{sink, stdout, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use lazy_static::lazy_static;
-use serde::{Serialize, Serializer};
-use clap::{Parser, ArgEnum};
-use num_enum::{TryFromPrimitive, TryFromPrimitiveError};
+use serde::Serialize;
+use clap::{Parser, ArgEnum, ErrorKind, IntoApp};
 use thiserror::Error;
-
-const DIVISION_POINTS: f32 = 50.0;
-const VOLTAGE_MEASUREMENT_DIVISOR: f32 = 1024f32;
-
-lazy_static! {
-    /// This is an example for using doc comment attributes
-    static ref TIME_SCALES: Vec<Scale<Second>> = vec![
-        Scale { value: 50.0, scale: 0, unit: Second },
-        Scale { value: 20.0, scale: 0, unit: Second },
-        Scale { value: 10.0, scale: 0, unit: Second },
-        Scale { value: 5.0, scale: 0, unit: Second },
-        Scale { value: 2.0, scale: 0, unit: Second },
-        Scale { value: 1.0, scale: 0, unit: Second },
-        Scale { value: 500.0, scale: -3, unit: Second },
-        Scale { value: 200.0, scale: -3, unit: Second },
-        Scale { value: 100.0, scale: -3, unit: Second },
-        Scale { value: 50.0, scale: -3, unit: Second },
-        Scale { value: 20.0, scale: -3, unit: Second },
-        Scale { value: 10.0, scale: -3, unit: Second },
-        Scale { value: 5.0, scale: -3, unit: Second },
-        Scale { value: 2.0, scale: -3, unit: Second },
-        Scale { value: 1.0, scale: -3, unit: Second },
-        Scale { value: 500.0, scale: -6, unit: Second },
-        Scale { value: 200.0, scale: -6, unit: Second },
-        Scale { value: 100.0, scale: -6, unit: Second },
-        Scale { value: 50.0, scale: -6, unit: Second },
-        Scale { value: 20.0, scale: -6, unit: Second },
-        Scale { value: 10.0, scale: -6, unit: Second },
-        Scale { value: 5.0, scale: -6, unit: Second },
-        Scale { value: 2.0, scale: -6, unit: Second },
-        Scale { value: 1.0, scale: -6, unit: Second },
-        Scale { value: 500.0, scale: -9, unit: Second },
-        Scale { value: 200.0, scale: -9, unit: Second },
-        Scale { value: 100.0, scale: -9, unit: Second },
-        Scale { value: 50.0, scale: -9, unit: Second },
-        Scale { value: 20.0, scale: -9, unit: Second },
-        Scale { value: 10.0, scale: -9, unit: Second },
-        Scale { value: 5.0, scale: -9, unit: Second },
-        Scale { value: 2.0, scale: -9, unit: Second },
-        Scale { value: 1.0, scale: -9, unit: Second },
-    ];
-}
-
-lazy_static! {
-    /// This is an example for using doc comment attributes
-    static ref PROBE_SCALES: Vec<Scale<Volt>> = vec![
-        Scale { value: 5.0, scale: 0, unit: Volt },
-        Scale { value: 2.5, scale: 0, unit: Volt },
-        Scale { value: 1.0, scale: 0, unit: Volt },
-        Scale { value: 500.0, scale: -3, unit: Volt },
-        Scale { value: 200.0, scale: -3, unit: Volt },
-        Scale { value: 100.0, scale: -3, unit: Volt },
-        Scale { value: 50.0, scale: -3, unit: Volt },
-    ];
-}
+use fnirsi::{Base64File, Buffer, Calibration, CaptureContext, ChannelLayout, channel_metrics_summary, channel_metrics_summary_width, channel_to_columns_json, channel_to_dataframe_json, channel_to_split_json, ChannelMerge, ChartFormat, Data, digitize, export_sigrok, export_vcd, Extrapolation, frequency_sweep_analysis, FnirsiError, GlitchDetection, Interpolation, InterpolationMethod, LogicThresholds, midpoint_v, Normalization, OutputFormat, OutputFormatter, ParseOptions, Point, PulseCount, SpectrogramOptions, TimeHistogramOptions, TimeUnit, TimeUnitSelection, WordOrder, parse_capture, parse_data, parse_file_with_layout, read_calibration_file, render_ascii, render_waveform_chart, sample_rate_hz, split_buffers, strip_bom, to_canonical_json, to_columnar_json, validate_capture, write_arrow_ipc, write_feather, write_parquet, write_png, write_measurements_csv_header, write_measurements_csv_row, write_tektronix_csv};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
     output: Output,
-    file: String
+    /// Captures to convert, text formats write one document per capture and binary formats one file per capture
+    #[clap(required_unless_present = "batch")]
+    file: Vec<String>,
+    /// Also convert the captures matching this pattern, `*` matches any characters of the file name and `?` one of them
+    #[clap(long, value_name = "PATTERN")]
+    batch: Option<String>,
+    /// Channel feeding whatever the bode-plot output measures, defaults to 1. Only for the bode-plot output
+    #[clap(long, possible_values = ["1", "2"])]
+    input_ch: Option<u8>,
+    /// Channel measuring the output of whatever the bode-plot output measures, defaults to 2. Only for the bode-plot output
+    #[clap(long, possible_values = ["1", "2"])]
+    output_ch: Option<u8>,
+    /// File to write the output to, binary formats fall back to `output.<format>` and text formats to stdout
+    #[clap(long)]
+    output_file: Option<PathBuf>,
+    /// Sequence index of the first capture, incremented for every following capture. Defaults to 0 with several captures
+    #[clap(long)]
+    repeat: Option<u32>,
+    /// Encoding of the sample buffers of the raw output, `base64` encodes their little endian bytes
+    #[clap(long, arg_enum, default_value = "array")]
+    raw_samples_encoding: SamplesEncoding,
+    /// Layout of the points of every channel in the dataframe output, after the `orient` of pandas `read_json`
+    #[clap(long, arg_enum, default_value = "records")]
+    json_orient: JsonOrient,
+    /// Skip a UTF-8 or UTF-16 byte order mark at the start of the captures, as prepended by some text editors
+    #[clap(long)]
+    skip_bom: bool,
+    /// Parse the captures as holding the buffers of channel 1 and 2 only, whatever their size
+    #[clap(long = "force-2ch", conflicts_with = "force-4ch")]
+    force_2ch: bool,
+    /// Parse the captures as holding the buffers of channel 1 to 4, failing on shorter captures
+    #[clap(long = "force-4ch")]
+    force_4ch: bool,
+    /// Only parse and check the captures, printing whether each passed to stderr and exiting with 1 if any failed
+    #[clap(long)]
+    dry_run: bool,
+    /// Write the header offsets of every capture next to the ones their samples suggest, the frequency checks and the resolution of the capture as json instead of the output
+    #[clap(long)]
+    validate: bool,
+    /// Write every sample buffer of the captures with its offset in the file as json instead of the output
+    #[clap(long, conflicts_with = "validate")]
+    split_buffers: bool,
+    /// Characters per line of the text and ascii outputs, defaults to `$COLUMNS` and otherwise unlimited for text and 80 for ascii
+    #[clap(long)]
+    width: Option<usize>,
+    /// Lines of the plot of every channel in the ascii output
+    #[clap(long, default_value = "16")]
+    height: usize,
+    /// Log a summary of every channel
+    #[clap(short, long)]
+    verbose: bool,
+    /// Wrap the json output in an object under this key
+    #[clap(long)]
+    json_root: Option<String>,
+    /// Write the points of every channel of the parsed output as `times` and `voltages` arrays instead of objects
+    #[clap(long)]
+    columnar_points: bool,
+    /// Sort the keys of every json object so identical captures always produce identical json, for diffing the outputs
+    #[clap(long)]
+    canonicalize: bool,
+    /// Minimum normalized autocorrelation peak accepted when estimating a missing or implausible frequency
+    #[clap(long, default_value = "0.5")]
+    min_autocorrelation: f32,
+    /// Minimum number of samples every channel must have, defaults to the samples per channel of the buffer in the file layout
+    #[clap(long)]
+    min_samples: Option<usize>,
+    /// Sample buffer to build the points from, `zoom` is the 750 sample buffer
+    #[clap(long, arg_enum, default_value = "main")]
+    buffer: SampleBuffer,
+    /// Add the indices of the points of every channel on the vertical grid lines of the screen
+    #[clap(long)]
+    annotate_divisions: bool,
+    /// Correct the voltages of a channel as `v * GAIN + OFFSET_V`, replacing the calibration stored in `~/.config/fnirsi/calibration.toml`
+    #[clap(long, value_name = "chN=GAIN,OFFSET_V", multiple_occurrences = true)]
+    calibration: Vec<ChannelCalibration>,
+    /// Build the points from the samples in reverse order, for captures storing the newest sample first instead of the oldest
+    #[clap(long)]
+    reverse_samples: bool,
+    /// Index of the newest sample in the buffers of a roll mode capture, they are rotated to start with the oldest one
+    #[clap(long, value_name = "INDEX")]
+    roll_write_pointer: Option<usize>,
+    /// Seconds added to the time of every point, to line captures up with external logs
+    #[clap(long, default_value = "0", value_name = "SECONDS")]
+    time_origin: f32,
+    /// Unit of the times of the points, `auto` for the one of the time scale like `ns` at 20ns/div [default: s]
+    #[clap(long, arg_enum)]
+    select_time_unit: Option<SelectTimeUnit>,
+    /// Average this many consecutive points into one after the analyses, trading samples for resolution
+    #[clap(long, value_name = "FACTOR")]
+    oversample: Option<usize>,
+    /// Upsample the points by FACTOR after the analyses, interpolating the points in between with a line or a cubic spline
+    #[clap(long, number_of_values = 2, value_names = &["linear|cubic", "FACTOR"])]
+    interpolate: Vec<String>,
+    /// Add the points of every channel reduced to N display columns holding the lowest and highest voltage of their points
+    #[clap(long, value_name = "N")]
+    display_samples: Option<usize>,
+    /// Add the voltage scales the channels and the time scale the trigger source would read best at
+    #[clap(long)]
+    suggest_scales: bool,
+    /// `--interpolate` parsed once the arguments are
+    #[clap(skip)]
+    interpolation: Option<Interpolation>,
+    /// Order of the frequency words in the measurements, `hl` when the high word is the most significant
+    #[clap(long, arg_enum, default_value = "hl")]
+    frequency_word_order: FrequencyWordOrder,
+    /// Fail on suspicious header values and samples outside of the ADC range instead of warning about them
+    #[clap(long)]
+    strict: bool,
+    /// Read unknown probe values as 1x probes instead of failing
+    #[clap(long)]
+    lenient: bool,
+    /// Add the sample counts, the bytes read, the file size and the fallbacks taken to the parsed output
+    #[clap(long)]
+    diagnostics: bool,
+    /// Add the logic levels of a channel, high from above HIGH until below LOW
+    #[clap(long, value_name = "chN=HIGH,LOW", multiple_occurrences = true)]
+    digitize: Vec<ChannelThresholds>,
+    /// Add the words of a parallel bus whose bits are these digitized channels, least significant first like `1,2`
+    #[clap(long, value_name = "CHANNELS", use_delimiter = true, requires = "digitize")]
+    parallel_decode: Vec<u8>,
+    /// Add human readable strings like `250 mV` next to the voltage measurements
+    #[clap(long)]
+    human_measurements: bool,
+    /// Band around the final value of a step the signal has to stay in to be settled, in percent of the step
+    #[clap(long, default_value = "2")]
+    settling_tolerance: f32,
+    /// Fraction of the larger value the measurements compared by the consistency checks and `--compare-frequencies` may differ by
+    #[clap(long, default_value = "0.05", value_name = "FRACTION")]
+    tolerance: f32,
+    /// Subtract the average voltage from the points of every channel before any analysis
+    #[clap(long)]
+    remove_dc: bool,
+    /// Add the phase of channel 2 relative to channel 1 from their zero crossings
+    #[clap(long)]
+    phase: bool,
+    /// Add the magnitude squared coherence between both channels
+    #[clap(long)]
+    coherence: bool,
+    /// Add the transfer function from channel 1 as the input to channel 2 as the output, for Bode plots of a filter
+    #[clap(long)]
+    transfer_function: bool,
+    /// Add the period of every channel estimated from the peak of its FFT
+    #[clap(long = "fft")]
+    fft_period: bool,
+    /// Add the frequency of every channel measured by the device, from its zero crossings and from its FFT, and whether they agree
+    #[clap(long)]
+    compare_frequencies: bool,
+    /// Add the waveform of every channel, like `sine` or `square`, recognized from its points
+    #[clap(long)]
+    classify: bool,
+    /// Fit a sine to channel 1
+    #[clap(long)]
+    fit_sine: bool,
+    /// Add a histogram of the channel 1 voltages with this many bins
+    #[clap(long, value_name = "NUM_BINS")]
+    histogram: Option<usize>,
+    /// Add the points of every channel followed by this many autoregressive predictions
+    #[clap(long, value_name = "N")]
+    extrapolate: Option<usize>,
+    /// Order of the autoregressive model used by `--extrapolate`
+    #[clap(long, default_value = "16")]
+    extrapolate_order: usize,
+    /// Add the period, pulse widths and edge times of the channel 1 pulses crossing `--threshold`
+    #[clap(long, requires = "threshold")]
+    pulse_train: bool,
+    /// Add the excursions of channel 1 across `--threshold` returning within `--max-width` samples
+    #[clap(long, requires_all = &["threshold", "max-width"])]
+    glitch_detect: bool,
+    /// Widest glitch in samples
+    #[clap(long)]
+    max_width: Option<usize>,
+    /// Add histograms of the widths of the channel 1 pulses above and below `--threshold`
+    #[clap(long, requires = "threshold")]
+    time_histogram: bool,
+    /// Bins of the pulse width histograms
+    #[clap(long, default_value = "10")]
+    time_histogram_bins: usize,
+    /// Add the points of channel 1 minus (`sub`) or plus (`add`) channel 2
+    #[clap(long, arg_enum)]
+    merge_channels: Option<MergeChannels>,
+    /// Add channel 1 minus channel 2 as a channel of its own, scaled to fit the difference
+    #[clap(long)]
+    differential: bool,
+    /// Replace the points with their peak envelope decaying by `--decay` per sample
+    #[clap(long, requires = "decay")]
+    envelope_detect: bool,
+    /// Fraction of the envelope lost every sample the voltage stays below it
+    #[clap(long)]
+    decay: Option<f32>,
+    /// Scale the point voltages so the lowest is -1 and the highest 1
+    #[clap(long, conflicts_with = "normalize-scale")]
+    normalize: bool,
+    /// Scale the point voltages so the top and bottom of the screen are 1 and -1
+    #[clap(long)]
+    normalize_scale: bool,
+    /// Add a spectrogram of every channel
+    #[clap(long)]
+    spectrogram: bool,
+    /// Samples per spectrogram window
+    #[clap(long, default_value = "128")]
+    spectrogram_window: usize,
+    /// Samples between spectrogram windows, defaults to the window size
+    #[clap(long)]
+    spectrogram_hop: Option<usize>,
+    /// Count the pulses of channel 1 crossing `--threshold`
+    #[clap(long, requires = "threshold")]
+    pulse_count: bool,
+    /// Voltage threshold used for edge detection
+    #[clap(long)]
+    threshold: Option<f32>,
+    /// Width of the hysteresis band centered on the threshold
+    #[clap(long, default_value = "0")]
+    hysteresis: f32,
+    /// Count falling edges instead of rising ones
+    #[clap(long)]
+    active_low: bool
 }
 
 #[derive(Debug, ArgEnum, Clone)]
@@ -77,7 +235,39 @@ enum Output {
     #[clap(name = "raw")]
     Raw,
     #[clap(name = "parsed")]
-    Parsed
+    Parsed,
+    #[clap(name = "arrow")]
+    Arrow,
+    #[clap(name = "parquet")]
+    Parquet,
+    #[clap(name = "png")]
+    Png,
+    #[clap(name = "messagepack")]
+    Messagepack,
+    #[clap(name = "cbor")]
+    Cbor,
+    #[clap(name = "chart-png")]
+    ChartPng,
+    #[clap(name = "chart-svg")]
+    ChartSvg,
+    #[clap(name = "tektronix-csv")]
+    TektronixCsv,
+    #[clap(name = "text")]
+    Text,
+    #[clap(name = "measurements-csv")]
+    MeasurementsCsv,
+    #[clap(name = "ascii")]
+    Ascii,
+    #[clap(name = "feather")]
+    Feather,
+    #[clap(name = "dataframe")]
+    Dataframe,
+    #[clap(name = "sigrok")]
+    Sigrok,
+    #[clap(name = "vcd")]
+    Vcd,
+    #[clap(name = "bode-plot")]
+    BodePlot
 }
 
 impl FromStr for Output {
@@ -87,359 +277,518 @@ impl FromStr for Output {
         Ok(match s {
             "raw" => Output::Raw,
             "parsed" => Output::Parsed,
+            "arrow" => Output::Arrow,
+            "parquet" => Output::Parquet,
+            "png" => Output::Png,
+            "messagepack" => Output::Messagepack,
+            "cbor" => Output::Cbor,
+            "chart-png" => Output::ChartPng,
+            "chart-svg" => Output::ChartSvg,
+            "tektronix-csv" => Output::TektronixCsv,
+            "text" => Output::Text,
+            "measurements-csv" => Output::MeasurementsCsv,
+            "ascii" => Output::Ascii,
+            "feather" => Output::Feather,
+            "dataframe" => Output::Dataframe,
+            "sigrok" => Output::Sigrok,
+            "vcd" => Output::Vcd,
+            "bode-plot" => Output::BodePlot,
             other => return Err(OutputParseError(other.to_string()))
         })
     }
 }
 
-#[derive(Debug, Error)]
-#[error("The output type {0} is not supported")]
-struct OutputParseError(String);
-
-fn main() {
-    let args = Args::parse();
-    let file: File = FsFile::open(args.file).unwrap().read_le().unwrap();
-
-    match args.output {
-        Output::Raw => serde_json::to_writer(stdout(), &file),
-        Output::Parsed => {
-            let time_scale = file.header.time_scale.try_into().unwrap();
-            let chanel1_scale = file.header.channel1_scale.try_into().unwrap();
-            let channel2_scale = file.header.channel2_scale.try_into().unwrap();
-            let channel1_points = generate_points(&file.channel11, &chanel1_scale, &time_scale, file.header.channel1_offset);
-            let channel2_points = generate_points(&file.channel11, &channel2_scale, &time_scale, file.header.channel2_offset);
-
-            let data = Data {
-                trigger: Trigger {
-                    trigger_type: file.header.trigger_type.try_into().unwrap(),
-                    edge: file.header.trigger_edge.try_into().unwrap(),
-                    channel: file.header.trigger_channel.try_into().unwrap(),
-                    trigger_50: file.header.trigger_50.try_into().unwrap()
-                },
-                time_scale,
-                channel1: Channel {
-                    scale: chanel1_scale,
-                    coupling: file.header.channel1_coupling.try_into().unwrap(),
-                    attenuation: file.header.channel1_probe.try_into().unwrap(),
-                    measurements: ProcessedMeasurements {
-                        vmax: process_voltage_measurement(file.header.channel1_measurements.vmax),
-                        vmin: process_voltage_measurement(file.header.channel1_measurements.vmin),
-                        vavg: process_voltage_measurement(file.header.channel1_measurements.vavg),
-                        vrms: process_voltage_measurement(file.header.channel1_measurements.vrms),
-                        vpp: process_voltage_measurement(file.header.channel1_measurements.vpp),
-                        vp: process_voltage_measurement(file.header.channel1_measurements.vp),
-                        frequency: parse_frequency(file.header.channel1_measurements.frequency_high, file.header.channel1_measurements.frequency_low),
-                        cycle_ns: file.header.channel1_measurements.cycle_ns,
-                        time_plus_ns: file.header.channel1_measurements.time_plus_ns,
-                        time_minus_ns: file.header.channel1_measurements.time_minus_ns,
-                        duty_plus_percentage: file.header.channel1_measurements.duty_plus_percentage,
-                        duty_minus_percentage: file.header.channel1_measurements.duty_minus_percentage
-                    },
-                    points: channel1_points
-                },
-                channel2: Channel {
-                    scale: channel2_scale,
-                    coupling: file.header.channel2_coupling.try_into().unwrap(),
-                    attenuation: file.header.channel2_probe.try_into().unwrap(),
-                    measurements: ProcessedMeasurements {
-                        vmax: process_voltage_measurement(file.header.channel2_measurements.vmax),
-                        vmin: process_voltage_measurement(file.header.channel2_measurements.vmin),
-                        vavg: process_voltage_measurement(file.header.channel2_measurements.vavg),
-                        vrms: process_voltage_measurement(file.header.channel2_measurements.vrms),
-                        vpp: process_voltage_measurement(file.header.channel2_measurements.vpp),
-                        vp: process_voltage_measurement(file.header.channel2_measurements.vp),
-                        frequency: parse_frequency(file.header.channel2_measurements.frequency_high, file.header.channel2_measurements.frequency_low),
-                        cycle_ns: file.header.channel2_measurements.cycle_ns,
-                        time_plus_ns: file.header.channel2_measurements.time_plus_ns,
-                        time_minus_ns: file.header.channel2_measurements.time_minus_ns,
-                        duty_plus_percentage: file.header.channel2_measurements.duty_plus_percentage,
-                        duty_minus_percentage: file.header.channel2_measurements.duty_minus_percentage
-                    },
-                    points: channel2_points
-                }
-            };
-
-            serde_json::to_writer(stdout(), &data)
-        }
-    }.unwrap();
+#[derive(Debug, ArgEnum, Clone)]
+enum FrequencyWordOrder {
+    Hl,
+    Lh
 }
 
-fn parse_frequency(high: u16, low: u16) -> u32 {
-    ((high as u32) << 16) + low as u32
+#[derive(Debug, ArgEnum, Clone)]
+enum SampleBuffer {
+    Main,
+    Zoom
 }
 
-#[derive(Debug, Serialize)]
-struct Data {
-    trigger: Trigger,
-    time_scale: Scale<Second>,
-    channel1: Channel,
-    channel2: Channel,
+#[derive(Debug, ArgEnum, Clone)]
+enum SelectTimeUnit {
+    S,
+    Ms,
+    Us,
+    Ns,
+    Auto
 }
 
-#[derive(Debug, Serialize)]
-struct Channel {
-    scale: Scale<Volt>,
-    coupling: Coupling,
-    attenuation: Attenuation,
-    measurements: ProcessedMeasurements,
-    points: Vec<Point>
+#[derive(Debug, ArgEnum, Clone)]
+enum JsonOrient {
+    Records,
+    Split,
+    Columns
 }
 
-#[derive(Debug, Serialize)]
-struct Trigger {
-    trigger_type: TriggerType,
-    edge: TriggerEdge,
-    channel: TriggerChannel,
-    trigger_50: Trigger50
+#[derive(Debug, ArgEnum, Clone)]
+enum SamplesEncoding {
+    Array,
+    Base64
 }
 
-fn generate_points(values: &Vec<u16>, voltage_scale: &Scale<Volt>, time_scale: &Scale<Second>, offset: u16) -> Vec<Point> {
-    values.iter().enumerate().map(| (index, voltage)| Point {
-        time: (index as f32) * time_scale.get_scale()/ DIVISION_POINTS,
-        voltage: (*voltage as f32 - offset as f32) * voltage_scale.get_scale()/DIVISION_POINTS
-    }).collect()
+#[derive(Debug, ArgEnum, Clone)]
+enum MergeChannels {
+    Sub,
+    Add
 }
 
-fn process_voltage_measurement(measurement: u16) -> f32 {
-    (measurement as f32)/VOLTAGE_MEASUREMENT_DIVISOR
-}
+#[derive(Debug, Error)]
+#[error("The output type {0} is not supported")]
+struct OutputParseError(String);
 
-#[derive(Debug, Serialize)]
-struct Point {
-    time: f32,
-    voltage: f32
+#[derive(Debug, Clone)]
+struct ChannelCalibration {
+    channel: u8,
+    calibration: Calibration
 }
 
-#[derive(BinRead, Debug, Serialize)]
-#[br(little)]
-pub struct File {
-    header: Header,
-    #[br(count = 1500, seek_before = SeekFrom::Start(1000))]
-    channel11: Vec<u16>,
-    #[br(count = 1500)]
-    channel21: Vec<u16>,
-    #[br(count = 750)]
-    channel12: Vec<u16>,
-    #[br(count = 750)]
-    channel22: Vec<u16>
-}
+impl FromStr for ChannelCalibration {
+    type Err = CalibrationParseError;
 
-#[derive(BinRead, Debug, Serialize)]
-pub struct Header {
-    #[br(pad_before = 4)]
-    channel1_scale: u16,
-    #[br(pad_before = 2)]
-    channel1_coupling: u16,
-    channel1_probe: u16,
-    #[br(pad_before = 2)]
-    channel2_scale: u16,
-    #[br(pad_before = 2)]
-    channel2_coupling: u16,
-    channel2_probe: u16,
-    time_scale: u16,
-    scroll_speed: u16,
-    trigger_type: u16,
-    trigger_edge: u16,
-    trigger_channel: u16,
-    #[br(pad_before = 52)]
-    channel1_offset: u16,
-    channel2_offset: u16,
-    #[br(pad_before = 32)]
-    screen_brightness: u16,
-    grid_brightness: u16,
-    trigger_50: u16,
-    #[br(seek_before = SeekFrom::Start(208))]
-    channel1_measurements: Measurements,
-    #[br(seek_before = SeekFrom::Start(256))]
-    channel2_measurements: Measurements
-}
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (channel, gain, offset_v) = parse_channel_pair(s).ok_or_else(|| CalibrationParseError(s.to_string()))?;
 
-#[derive(BinRead, Debug, Serialize)]
-#[br(little)]
-pub struct Measurements {
-    #[br(pad_before = 2)]
-    vmax: u16,
-    #[br(pad_before = 2)]
-    vmin: u16,
-    #[br(pad_before = 2)]
-    vavg: u16,
-    #[br(pad_before = 2)]
-    vrms: u16,
-    #[br(pad_before = 2)]
-    vpp: u16,
-    #[br(pad_before = 2)]
-    vp: u16,
-    frequency_high: u16,
-    frequency_low: u16,
-    #[br(pad_before = 2)]
-    cycle_ns: u16,
-    #[br(pad_before = 2)]
-    time_plus_ns: u16,
-    #[br(pad_before = 2)]
-    time_minus_ns: u16,
-    #[br(pad_before = 2)]
-    duty_plus_percentage: u16,
-    #[br(pad_before = 2)]
-    duty_minus_percentage: u16
+        Ok(ChannelCalibration { channel, calibration: Calibration { gain, offset_v } })
+    }
 }
 
-#[derive(Debug, Serialize)]
-pub struct ProcessedMeasurements {
-    vmax: f32,
-    vmin: f32,
-    vavg: f32,
-    vrms: f32,
-    vpp: f32,
-    vp: f32,
-    frequency: u32,
-    cycle_ns: u16,
-    time_plus_ns: u16,
-    time_minus_ns: u16,
-    duty_plus_percentage: u16,
-    duty_minus_percentage: u16
-}
+#[derive(Debug, Error)]
+#[error("The calibration {0} is not of the form ch1=<gain>,<offset_v> or ch2=<gain>,<offset_v>")]
+struct CalibrationParseError(String);
 
-trait Unit: Display + Clone + Copy {}
+#[derive(Debug, Clone)]
+struct ChannelThresholds {
+    channel: u8,
+    thresholds: LogicThresholds
+}
 
-#[derive(Clone, Copy)]
-struct Volt;
+impl FromStr for ChannelThresholds {
+    type Err = ThresholdsParseError;
 
-impl Unit for Volt {}
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (channel, high_v, low_v) = parse_channel_pair(s).ok_or_else(|| ThresholdsParseError(s.to_string()))?;
 
-impl Serialize for Volt {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-        str::serialize("Volt", serializer)
+        Ok(ChannelThresholds { channel, thresholds: LogicThresholds { high_v, low_v } })
     }
 }
 
-impl Display for Volt {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "V")
+#[derive(Debug, Error)]
+#[error("The thresholds {0} are not of the form ch1=<high>,<low> or ch2=<high>,<low>")]
+struct ThresholdsParseError(String);
+
+/// Splits `ch1=<a>,<b>` or `ch2=<a>,<b>` into the channel and both numbers
+fn parse_channel_pair(s: &str) -> Option<(u8, f32, f32)> {
+    let (channel, values) = s.split_once('=')?;
+    let channel = match channel {
+        "ch1" => 1,
+        "ch2" => 2,
+        _ => return None
+    };
+    let (first, second) = values.split_once(',')?;
+
+    Some((channel, first.trim().parse().ok()?, second.trim().parse().ok()?))
+}
+
+/// Reads the method and the factor of `--interpolate`
+fn parse_interpolation(values: &[String]) -> Result<Option<Interpolation>, InterpolationParseError> {
+    let (method, factor) = match values {
+        [] => return Ok(None),
+        [method, factor] => (method, factor),
+        _ => return Err(InterpolationParseError(values.join(" ")))
+    };
+    let method = match method.as_str() {
+        "linear" => InterpolationMethod::Linear,
+        "cubic" => InterpolationMethod::Cubic,
+        _ => return Err(InterpolationParseError(values.join(" ")))
+    };
+
+    match factor.parse() {
+        Ok(factor) if factor > 0 => Ok(Some(Interpolation { method, factor })),
+        _ => Err(InterpolationParseError(values.join(" ")))
     }
 }
 
-#[derive(Clone, Copy)]
-struct Second;
+/// The files matching the `--batch` pattern in the order of their names, only the file name may hold the wildcards
+fn expand_batch(pattern: &str) -> std::io::Result<Vec<String>> {
+    let pattern = Path::new(pattern);
+    let name_pattern: Vec<char> = pattern.file_name().map(|name| name.to_string_lossy().chars().collect()).unwrap_or_default();
+    let directory = pattern.parent().filter(|parent| !parent.as_os_str().is_empty());
+    let mut files = std::fs::read_dir(directory.unwrap_or_else(|| Path::new(".")))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
 
-impl Unit for Second {}
+            (entry.path().is_file() && wildcard_matches(&name_pattern, &name.chars().collect::<Vec<_>>()))
+                .then(|| directory.map_or_else(|| PathBuf::from(&name), |directory| directory.join(&name)).to_string_lossy().into_owned())
+        })
+        .collect::<Vec<_>>();
 
-impl Serialize for Second {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-        str::serialize("Second", serializer)
-    }
+    files.sort();
+    Ok(files)
 }
 
-impl Display for Second {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "s")
+fn wildcard_matches(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => wildcard_matches(&pattern[1..], name) || (!name.is_empty() && wildcard_matches(pattern, &name[1..])),
+        (Some('?'), Some(_)) => wildcard_matches(&pattern[1..], &name[1..]),
+        (Some(expected), Some(actual)) if expected == actual => wildcard_matches(&pattern[1..], &name[1..]),
+        _ => false
     }
 }
 
-#[derive(Clone, Serialize, Copy)]
-struct Scale<T: Unit> {
-    value: f32,
-    scale: i32,
-    unit: T
-}
+#[derive(Debug, Error)]
+#[error("The interpolation {0} is not of the form <linear|cubic> <factor> with a factor of at least 1")]
+struct InterpolationParseError(String);
+
+fn main() {
+    let mut args = Args::parse();
 
-impl <T: Unit> Scale<T> {
-    fn get_scale(&self) -> f32 {
-        self.value * 10_f32.powi(self.scale)
+    args.interpolation = parse_interpolation(&args.interpolate)
+        .unwrap_or_else(|error| Args::into_app().error(ErrorKind::InvalidValue, error).exit());
+    if !matches!(args.output, Output::BodePlot) && (args.input_ch.is_some() || args.output_ch.is_some()) {
+        Args::into_app().error(ErrorKind::ArgumentConflict, "--input-ch and --output-ch only apply to the bode-plot output").exit();
     }
-}
+    if let Some(pattern) = &args.batch {
+        match expand_batch(pattern) {
+            Ok(files) if files.is_empty() => Args::into_app().error(ErrorKind::InvalidValue, format!("No capture matches {}", pattern)).exit(),
+            Ok(files) => args.file.extend(files),
+            Err(error) => Args::into_app().error(ErrorKind::Io, format!("{}: {}", pattern, error)).exit()
+        }
+    }
+
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(if args.verbose { "info" } else { "warn" })).init();
 
-impl <T: Unit> Display for Scale<T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}{}", self.value, match self.scale {
-            0 => "",
-            -3 => "m",
-            -6 => "u",
-            -9 => "n",
-            other => unreachable!("Unexpected scale {}", other)
-        }, self.unit)
+    if args.dry_run {
+        if !dry_run(&args) {
+            std::process::exit(1);
+        }
+        return;
     }
-}
 
-impl <T: Unit> Debug for Scale<T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Scale {{ value {}, scale: {}, string: {} }}", self.value, self.scale, self)
+    match run(args) {
+        Ok(()) => {},
+        // Whoever reads the output has all they want
+        Err(error) if error.is_broken_pipe() => {},
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
     }
 }
 
-impl TryFromPrimitive for Scale<Volt> {
-    type Primitive = u16;
-    const NAME: &'static str = "Scale<Volt>";
-
-    fn try_from_primitive(number: Self::Primitive) -> Result<Self, TryFromPrimitiveError<Self>> {
-        PROBE_SCALES.get(number as usize).map(Clone::clone).ok_or_else(|| TryFromPrimitiveError { number })
+fn run(args: Args) -> Result<(), FnirsiError> {
+    let options = parse_options(&args)?;
+    if let (Output::Cbor, None) = (&args.output, &args.output_file) {
+        if stdout().is_terminal() {
+            log::warn!("Writing binary cbor to the terminal, use --output-file or redirect stdout");
+        }
     }
-}
 
-impl TryFrom<u16> for Scale<Volt> {
-    type Error = TryFromPrimitiveError<Self>;
+    let mut writer: Box<dyn Write> = match args.output {
+        _ if args.validate || args.split_buffers => open_output(args.output_file.as_deref())?,
+        Output::Raw | Output::Parsed | Output::Messagepack | Output::Cbor | Output::Text | Output::MeasurementsCsv | Output::Ascii | Output::Dataframe | Output::BodePlot => open_output(args.output_file.as_deref())?,
+        _ => Box::new(sink())
+    };
 
-    fn try_from(value: u16) -> Result<Self, Self::Error> {
-        TryFromPrimitive::try_from_primitive(value)
+    if let Output::MeasurementsCsv = args.output {
+        write_measurements_csv_header(&mut writer)?;
     }
-}
+    // The bode plot is a single document built from every capture
+    let mut sweep = Vec::new();
+
+    for (index, path) in args.file.iter().enumerate() {
+        let sequence_index = args.repeat.or_else(|| (args.file.len() > 1).then_some(0)).map(|start| start + index as u32);
+        let bytes = std::fs::read(path)?;
+        let capture = if args.skip_bom { strip_bom(&bytes) } else { &bytes };
+        let context = CaptureContext {
+            file_size: Some(bytes.len()),
+            skipped_bytes: bytes.len() - capture.len(),
+            ..CaptureContext::new(parse_file_with_layout(capture, options.channel_layout.unwrap_or_else(|| ChannelLayout::detect(capture)))?, options.clone())
+        };
+        let data = || parse_data(&context).map(|data| {
+            log::info!("{}: CH1: {}", path, channel_metrics_summary(&data.channel1));
+            log::info!("{}: CH2: {}", path, channel_metrics_summary(&data.channel2));
+            Data { sequence_index, ..data }
+        });
+
+        if args.validate {
+            write_json(&validate_capture(&context)?, &args, &mut writer)?;
+            continue;
+        }
+        if args.split_buffers {
+            write_json(&split_buffers(&context), &args, &mut writer)?;
+            continue;
+        }
 
-impl TryFromPrimitive for Scale<Second> {
-    type Primitive = u16;
-    const NAME: &'static str = "Scale<Second>";
+        match args.output {
+            Output::Raw => match args.raw_samples_encoding {
+                SamplesEncoding::Array => write_json(&context.file, &args, &mut writer),
+                SamplesEncoding::Base64 => write_json(&Base64File::from(&context.file), &args, &mut writer)
+            },
+            Output::Parsed if args.columnar_points => data().and_then(|data| write_json(&to_columnar_json(&data)?, &args, &mut writer)),
+            Output::Parsed => data().and_then(|data| write_json(&data, &args, &mut writer)),
+            Output::Arrow => data().and_then(|data| write_arrow_ipc(&data, &output_path(&args, "output.arrow", sequence_index))),
+            Output::Feather => data().and_then(|data| write_feather(&data, &output_path(&args, "output.feather", sequence_index))),
+            Output::Dataframe => data().and_then(|data| {
+                let orient = match args.json_orient {
+                    JsonOrient::Records => channel_to_dataframe_json,
+                    JsonOrient::Split => channel_to_split_json,
+                    JsonOrient::Columns => channel_to_columns_json
+                };
+
+                write_json(&serde_json::json!({ "channel1": orient(&data.channel1), "channel2": orient(&data.channel2) }), &args, &mut writer)
+            }),
+            Output::Parquet => data().and_then(|data| write_parquet(&data, &output_path(&args, "output.parquet", sequence_index))),
+            Output::Png => data().and_then(|data| write_png(&data, &output_path(&args, "output.png", sequence_index))),
+            Output::Sigrok => data().and_then(|data| export_sigrok(&data, &output_path(&args, "output.sr", sequence_index))),
+            Output::BodePlot => data().map(|data| sweep.push((PathBuf::from(path), data))),
+            Output::Vcd => data().and_then(|data| {
+                // The thresholds of `--digitize`, otherwise `--threshold` with its hysteresis or the middle of the swing of the channel
+                let digitized = |digital: &Option<Vec<bool>>, points: &[Point]| digital.clone().unwrap_or_else(|| {
+                    let threshold_v = args.threshold.or_else(|| midpoint_v(points)).unwrap_or(0.0);
+                    digitize(points, threshold_v + args.hysteresis / 2.0, threshold_v - args.hysteresis / 2.0)
+                });
+
+                export_vcd(
+                    &data,
+                    &digitized(&data.channel1_digital, &data.channel1.points),
+                    &digitized(&data.channel2_digital, &data.channel2.points),
+                    sample_rate_hz(&data.time_scale) as f64,
+                    &output_path(&args, "output.vcd", sequence_index)
+                )
+            }),
+            Output::Messagepack => data().and_then(|data| OutputFormat::Messagepack.write(&data, &mut writer)),
+            Output::Cbor => data().and_then(|data| OutputFormat::Cbor.write(&data, &mut writer)),
+            Output::ChartPng => data().and_then(|data| render_waveform_chart(&data, &output_path(&args, "chart.png", sequence_index), ChartFormat::Png)),
+            Output::ChartSvg => data().and_then(|data| render_waveform_chart(&data, &output_path(&args, "chart.svg", sequence_index), ChartFormat::Svg)),
+            Output::TektronixCsv => data().and_then(|data| {
+                let path = output_path(&args, "output.csv", sequence_index);
+
+                [1, 2].iter().try_for_each(|channel| write_tektronix_csv(&data, *channel, &mut BufWriter::new(FsFile::create(channel_path(&path, *channel))?)))
+            }),
+            Output::MeasurementsCsv => data().and_then(|data| write_measurements_csv_row(path, &data, &mut writer)),
+            Output::Ascii => data().and_then(|data| {
+                if args.file.len() > 1 {
+                    writeln!(writer, "{}", path)?;
+                }
+                Ok(write!(writer, "{}", render_ascii(&data, terminal_width(&args).unwrap_or(80), args.height, context.model.layout().vertical_divisions as f32))?)
+            }),
+            Output::Text => data().and_then(|data| {
+                let width = terminal_width(&args).unwrap_or(usize::MAX);
 
-    fn try_from_primitive(number: Self::Primitive) -> Result<Self, TryFromPrimitiveError<Self>> {
-        TIME_SCALES.get(number as usize).map(Clone::clone).ok_or_else(|| TryFromPrimitiveError { number })
+                if args.file.len() > 1 {
+                    writeln!(writer, "{}", path)?;
+                }
+                // The prefix takes 5 of the characters
+                writeln!(writer, "CH1: {}", channel_metrics_summary_width(&data.channel1, width.saturating_sub(5)))?;
+                Ok(writeln!(writer, "CH2: {}", channel_metrics_summary_width(&data.channel2, width.saturating_sub(5)))?)
+            })
+        }?;
     }
+
+    if let Output::BodePlot = args.output {
+        if !args.validate && !args.split_buffers {
+            write_json(&frequency_sweep_analysis(&sweep, args.input_ch.unwrap_or(1), args.output_ch.unwrap_or(2)), &args, &mut writer)?;
+        }
+    }
+
+    Ok(writer.flush()?)
+}
+
+/// Runs every capture through `parse_data` without writing anything, returns whether all of them passed
+fn dry_run(args: &Args) -> bool {
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            return false;
+        }
+    };
+
+    args.file.iter().fold(true, |passed, path| {
+        let result = std::fs::read(path).map_err(FnirsiError::from)
+            .and_then(|bytes| parse_capture(if args.skip_bom { strip_bom(&bytes) } else { &bytes }, options.clone()));
+
+        match result {
+            Ok(_) => {
+                eprintln!("{}: ok", path);
+                passed
+            }
+            Err(error) => {
+                eprintln!("{}: failed: {}", path, error);
+                false
+            }
+        }
+    })
 }
 
-impl TryFrom<u16> for Scale<Second> {
-    type Error = TryFromPrimitiveError<Self>;
+fn parse_options(args: &Args) -> Result<ParseOptions, FnirsiError> {
+    let mut calibration = match calibration_path() {
+        Some(path) if path.exists() => read_calibration_file(&path)?,
+        _ => [Calibration::default(); 2]
+    };
 
-    fn try_from(value: u16) -> Result<Self, Self::Error> {
-        TryFromPrimitive::try_from_primitive(value)
+    for channel_calibration in &args.calibration {
+        calibration[channel_calibration.channel as usize - 1] = channel_calibration.calibration;
+    }
+    let mut digitize = [None; 2];
+
+    for channel_thresholds in &args.digitize {
+        digitize[channel_thresholds.channel as usize - 1] = Some(channel_thresholds.thresholds);
     }
-}
 
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
-#[repr(u16)]
-enum Coupling {
-    DC = 0, AC
+    Ok(ParseOptions {
+        channel_layout: match (args.force_2ch, args.force_4ch) {
+            (true, _) => Some(ChannelLayout::TwoChannel),
+            (_, true) => Some(ChannelLayout::FourChannel),
+            _ => None
+        },
+        min_autocorrelation: args.min_autocorrelation,
+        min_samples: args.min_samples,
+        buffer: match args.buffer {
+            SampleBuffer::Main => Buffer::Main,
+            SampleBuffer::Zoom => Buffer::Zoom
+        },
+        annotate_divisions: args.annotate_divisions,
+        attenuation_override: None,
+        scale_override: None,
+        time_scale_override: None,
+        calibration,
+        reverse_samples: args.reverse_samples,
+        roll_write_pointer: args.roll_write_pointer,
+        time_origin_s: args.time_origin,
+        time_unit: args.select_time_unit.as_ref().map(|unit| match unit {
+            SelectTimeUnit::S => TimeUnitSelection::Fixed(TimeUnit::Seconds),
+            SelectTimeUnit::Ms => TimeUnitSelection::Fixed(TimeUnit::Milliseconds),
+            SelectTimeUnit::Us => TimeUnitSelection::Fixed(TimeUnit::Microseconds),
+            SelectTimeUnit::Ns => TimeUnitSelection::Fixed(TimeUnit::Nanoseconds),
+            SelectTimeUnit::Auto => TimeUnitSelection::Auto
+        }),
+        strict: args.strict,
+        lenient: args.lenient,
+        diagnostics: args.diagnostics,
+        digitize,
+        parallel_decode: (!args.parallel_decode.is_empty()).then(|| args.parallel_decode.clone()),
+        oversample: args.oversample,
+        interpolation: args.interpolation,
+        display_samples: args.display_samples,
+        suggest_scales: args.suggest_scales,
+        frequency_word_order: match args.frequency_word_order {
+            FrequencyWordOrder::Hl => WordOrder::HighLow,
+            FrequencyWordOrder::Lh => WordOrder::LowHigh
+        },
+        remove_dc: args.remove_dc,
+        human_measurements: args.human_measurements,
+        settling_tolerance_percent: args.settling_tolerance,
+        tolerance: args.tolerance,
+        phase: args.phase,
+        coherence: args.coherence,
+        transfer_function: args.transfer_function,
+        fft_period: args.fft_period,
+        compare_frequencies: args.compare_frequencies,
+        classify: args.classify,
+        fit_sine: args.fit_sine,
+        histogram_bins: args.histogram,
+        pulse_count: match (args.pulse_count, args.threshold) {
+            (true, Some(threshold_v)) => Some(PulseCount { threshold_v, hysteresis_v: args.hysteresis, active_high: !args.active_low }),
+            _ => None
+        },
+        extrapolation: args.extrapolate.map(|num_extra_samples| Extrapolation { num_extra_samples, order: args.extrapolate_order }),
+        pulse_train_threshold_v: args.threshold.filter(|_| args.pulse_train),
+        glitch_detection: match (args.glitch_detect, args.max_width, args.threshold) {
+            (true, Some(max_width_samples), Some(threshold_v)) => Some(GlitchDetection { max_width_samples, threshold_v }),
+            _ => None
+        },
+        time_histogram: match (args.time_histogram, args.threshold) {
+            (true, Some(threshold_v)) => Some(TimeHistogramOptions { threshold_v, num_bins: args.time_histogram_bins }),
+            _ => None
+        },
+        merge_channels: args.merge_channels.as_ref().map(|merge| match merge {
+            MergeChannels::Sub => ChannelMerge::Sub,
+            MergeChannels::Add => ChannelMerge::Add
+        }),
+        differential: args.differential,
+        envelope_decay: args.decay.filter(|_| args.envelope_detect),
+        normalization: match (args.normalize, args.normalize_scale) {
+            (true, _) => Some(Normalization::MinMax),
+            (_, true) => Some(Normalization::Scale),
+            _ => None
+        },
+        spectrogram: args.spectrogram.then(|| SpectrogramOptions {
+            window_size: args.spectrogram_window,
+            hop_size: args.spectrogram_hop.unwrap_or(args.spectrogram_window)
+        })
+    })
 }
 
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
-#[repr(u16)]
-enum Attenuation {
-    OneX = 0,
-    TenX,
-    OneHundredX
+/// `calibration.toml` in the fnirsi directory of `$XDG_CONFIG_HOME`, or of `~/.config` when it isn't set
+fn calibration_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(config.join("fnirsi").join("calibration.toml"))
 }
 
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
-#[repr(u16)]
-enum ScrollSpeed {
-    Fast = 0, Slow
+fn open_output(path: Option<&Path>) -> Result<Box<dyn Write>, FnirsiError> {
+    Ok(match path {
+        Some(path) => Box::new(FsFile::create(path)?),
+        None => Box::new(stdout())
+    })
 }
 
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
-#[repr(u16)]
-enum TriggerType {
-    Auto = 0, Single, Normal
+/// The output file of a binary format, with the sequence index before the extension when converting several captures
+fn output_path(args: &Args, default: &str, sequence_index: Option<u32>) -> PathBuf {
+    let path = args.output_file.clone().unwrap_or_else(|| PathBuf::from(default));
+
+    match sequence_index {
+        Some(sequence_index) if args.file.len() > 1 => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let file_name = match path.extension() {
+                Some(extension) => format!("{}.{}.{}", stem, sequence_index, extension.to_string_lossy()),
+                None => format!("{}.{}", stem, sequence_index)
+            };
+
+            path.with_file_name(file_name)
+        }
+        _ => path
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
-#[repr(u16)]
-enum TriggerEdge {
-    Rising = 0, Falling
+/// The file of one channel of a format writing every channel to its own file, `output.csv` becomes `output.ch1.csv`
+fn channel_path(path: &Path, channel: u8) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}.ch{}.{}", stem, channel, extension.to_string_lossy()),
+        None => format!("{}.ch{}", stem, channel)
+    };
+
+    path.with_file_name(file_name)
 }
 
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
-#[repr(u16)]
-enum TriggerChannel {
-    Channel1 = 0, Channel2
+/// `--width`, or the `$COLUMNS` of the terminal when it isn't given
+fn terminal_width(args: &Args) -> Option<usize> {
+    args.width.or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
 }
 
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Serialize)]
-#[repr(u16)]
-enum Trigger50 {
-    On = 0, Off
+fn write_json<T: Serialize>(value: &T, args: &Args, writer: &mut impl Write) -> Result<(), FnirsiError> {
+    match (&args.json_root, args.canonicalize) {
+        (Some(root), true) => writer.write_all(to_canonical_json(&BTreeMap::from([(root, value)]))?.as_bytes())?,
+        (None, true) => writer.write_all(to_canonical_json(value)?.as_bytes())?,
+        (Some(root), false) => serde_json::to_writer(&mut *writer, &BTreeMap::from([(root, value)]))?,
+        (None, false) => serde_json::to_writer(&mut *writer, value)?
+    }
+
+    if args.file.len() > 1 {
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
 }
+
@@ -0,0 +1,4085 @@
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{Display, Formatter};
+use binread::BinReaderExt;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use num_enum::TryFromPrimitiveError;
+use levenberg_marquardt::LeastSquaresProblem;
+use crate::capture::*;
+
+/// Noise of this fraction of the signal span or more scores 0
+const MAX_NOISE_RATIO: f32 = 0.1;
+/// Samples per Welch segment when estimating the coherence, consecutive segments overlap by half
+pub const COHERENCE_SEGMENT_SIZE: usize = 256;
+/// Bins where the input of a transfer function is this far below its strongest bin hold too little stimulus to measure
+pub const TRANSFER_FUNCTION_NOISE_FLOOR_DB: f64 = -60.0;
+/// Highest brightness the scope menus allow
+const MAX_BRIGHTNESS: u16 = 100;
+/// Channels whose average is at least this fraction of their RMS are classified as DC
+const DC_MEAN_TO_RMS: f32 = 0.9;
+/// Channels whose average is at most this fraction of their RMS are classified as AC
+const AC_MEAN_TO_RMS: f32 = 0.1;
+/// Divisions of the 8 the `vpp` should span with a suggested scale, the geometric middle of 4 to 6
+const SUGGESTED_VPP_DIVISIONS: f64 = 4.898979485566356;
+/// Complete cycles the screen should show with a suggested time scale, the geometric middle of 2 to 5
+const SUGGESTED_CYCLES: f64 = 3.1622776601683795;
+/// Signals whose AC RMS is at most this fraction of their absolute average are detected as DC
+const DC_MAX_AC_TO_MEAN: f64 = 0.1;
+/// Signals with less of their AC power in the fundamental and its harmonics are detected as noise
+const MIN_HARMONIC_POWER_FRACTION: f64 = 0.5;
+/// Periods of the fundamental `detect_signal_type` needs to resolve the harmonics, fewer leave the shape unknown
+const MIN_CLASSIFIED_PERIODS: f64 = 3.0;
+/// Total harmonic distortion a sine stays below, a triangle has 12% and a square 48%
+const SINE_MAX_THD: f64 = 0.05;
+/// Total harmonic distortion a square or a sawtooth, with 80%, is above
+const SQUARE_MIN_THD: f64 = 0.4;
+/// Difference between the fractions of the time rising and falling a sawtooth is above, a triangle rises half of the time
+const SAWTOOTH_MIN_ASYMMETRY: f64 = 0.5;
+/// Fraction of the larger value two measurements that should agree may differ by, the default of `ParseOptions::tolerance`
+pub const MEASUREMENT_TOLERANCE: f32 = 0.05;
+/// Fraction of the largest value a histogram spreads over a single bin, the rounding of the interpolated edge times
+/// leaves pulses of the same width this far apart
+const HISTOGRAM_RESOLUTION: f32 = 1e-4;
+
+#[derive(Debug, Error)]
+pub enum FnirsiError {
+    #[error("Could not read or write the file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the file: {0}")]
+    Parse(#[from] binread::Error),
+    #[error("Unknown scale: {0}")]
+    InvalidScale(#[from] TryFromPrimitiveError<Scale<Volt>>),
+    #[error("Unknown time scale: {0}")]
+    InvalidTimeScale(#[from] TryFromPrimitiveError<Scale<Second>>),
+    #[error("Unknown coupling: {0}")]
+    InvalidCoupling(#[from] TryFromPrimitiveError<Coupling>),
+    #[error("Unknown attenuation: {0}")]
+    InvalidAttenuation(#[from] TryFromPrimitiveError<Attenuation>),
+    #[error("Unknown scroll speed: {0}")]
+    InvalidScrollSpeed(#[from] TryFromPrimitiveError<ScrollSpeed>),
+    #[error("Unknown trigger type: {0}")]
+    InvalidTriggerType(#[from] TryFromPrimitiveError<TriggerType>),
+    #[error("Unknown trigger edge: {0}")]
+    InvalidTriggerEdge(#[from] TryFromPrimitiveError<TriggerEdge>),
+    #[error("Unknown trigger channel: {0}")]
+    InvalidTriggerChannel(#[from] TryFromPrimitiveError<TriggerChannel>),
+    #[error("Unknown trigger 50%: {0}")]
+    InvalidTrigger50(#[from] TryFromPrimitiveError<Trigger50>),
+    #[error("Could not parse the name: {0}")]
+    InvalidName(#[from] InvalidEnumValue),
+    #[error("Could not serialize the output: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Could not serialize the messagepack output: {0}")]
+    Messagepack(#[from] rmp_serde::encode::Error),
+    #[error("Could not serialize the cbor output: {0}")]
+    Cbor(#[from] ciborium::ser::Error<std::io::Error>),
+    #[error("Could not parse the calibration file: {0}")]
+    Calibration(#[from] toml::de::Error),
+    #[cfg(feature = "arrow")]
+    #[error("Could not write the arrow output: {0}")]
+    Arrow(#[from] arrow::error::ArrowError),
+    #[cfg(feature = "parquet")]
+    #[error("Could not write the parquet output: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[cfg(feature = "png")]
+    #[error("Could not write the png output: {0}")]
+    Image(#[from] image::ImageError),
+    #[cfg(feature = "plotters")]
+    #[error("Could not draw the chart: {0}")]
+    Chart(String),
+    #[cfg(feature = "sigrok")]
+    #[error("Could not write the sigrok output: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("The {0} output requires building with the `{0}` feature")]
+    FeatureDisabled(&'static str),
+    #[error("Channel {channel} has {samples} samples but at least {min_samples} are required")]
+    TooFewSamples { channel: u8, samples: usize, min_samples: usize },
+    #[error("Channel 1 has {channel1} samples and channel 2 {channel2}, merging requires the same number of samples")]
+    SampleCountMismatch { channel1: usize, channel2: usize },
+    #[error("The header field {field} has the unknown value {value}")]
+    InvalidHeaderValue { field: &'static str, value: u16 },
+    #[error("Channel {channel} probe value {value} is not a known attenuation")]
+    UnknownAttenuation { channel: u8, value: u16 },
+    #[error("The header field {} has the suspicious value {}: {}", .0.field, .0.value, .0.message)]
+    InconsistentHeader(ConsistencyWarning),
+    #[error("Channel {channel} has {count} samples outside of the ADC range 0..=1023")]
+    SamplesOutOfRange { channel: u8, count: usize },
+    #[error("Channel {0} is a bit of the parallel bus but isn't digitized")]
+    NotDigitized(u8),
+    #[error("The sigrok output requires at least one digitized channel")]
+    NothingDigitized,
+    #[error("There is no channel {0}, the capture has channels 1 and 2")]
+    UnknownChannel(u8),
+    #[error("The scale {0} is not in the scale tables of the header")]
+    ScaleNotInTable(String)
+}
+
+impl FnirsiError {
+    /// Whether writing failed because the reader went away, like `head` does once it has read enough
+    pub fn is_broken_pipe(&self) -> bool {
+        if let FnirsiError::Cbor(ciborium::ser::Error::Io(error)) = self {
+            return error.kind() == std::io::ErrorKind::BrokenPipe;
+        }
+
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(self);
+
+        while let Some(error) = source {
+            if let Some(error) = error.downcast_ref::<std::io::Error>() {
+                return error.kind() == std::io::ErrorKind::BrokenPipe;
+            }
+            source = error.source();
+        }
+
+        false
+    }
+}
+
+/// Options for turning a `File` into `Data`, the analyses that are skipped by default are only run when enabled
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Minimum normalized autocorrelation peak accepted when estimating a missing or implausible frequency
+    pub min_autocorrelation: f32,
+    /// Minimum number of samples every channel must have, defaults to the samples per channel of the buffer in the file layout
+    pub min_samples: Option<usize>,
+    /// Sample buffer the points and the analyses are built from
+    pub buffer: Buffer,
+    /// Add the indices of the points on the vertical grid lines of the screen to every channel
+    pub annotate_divisions: bool,
+    /// Attenuation of channel 1 and 2 to report instead of the header ones, for probes the scope wasn't set up for
+    pub attenuation_override: Option<[Attenuation; 2]>,
+    /// Vertical scale of channel 1 and 2 used instead of the header ones
+    pub scale_override: Option<[Scale<Volt>; 2]>,
+    /// Time scale used instead of the header one
+    pub time_scale_override: Option<Scale<Second>>,
+    /// Gain and offset correcting the voltages of channel 1 and 2, applied to the points before anything else
+    pub calibration: [Calibration; 2],
+    /// Build the points from the samples in reverse order, for captures storing the newest sample first
+    pub reverse_samples: bool,
+    /// Index of the newest sample in the circular buffers of a roll mode capture, the header has no field holding it.
+    /// The buffers are rotated to start with the oldest sample before the points are built, captures not in roll mode are left as they are
+    pub roll_write_pointer: Option<usize>,
+    /// Added to the time of every output point to place the capture on an absolute timeline.
+    /// The times are `f32` so origins of days or more leave only millisecond or coarser resolution
+    pub time_origin_s: f32,
+    /// Unit the times of the points are converted to once everything else is done, seconds when unset
+    pub time_unit: Option<TimeUnitSelection>,
+    /// Fail on the first `verify_header_consistency` warning or sample outside of the ADC range instead of logging them
+    pub strict: bool,
+    /// Read probe values that are no known attenuation as 1x probes instead of failing
+    pub lenient: bool,
+    /// Add the sample counts, sizes and fallbacks of the parse
+    pub diagnostics: bool,
+    /// Add the logic levels of channel 1 and 2 digitized with these thresholds
+    pub digitize: [Option<LogicThresholds>; 2],
+    /// Channels carrying the bits of a parallel bus from the least significant up, each has to be digitized
+    pub parallel_decode: Option<Vec<u8>>,
+    /// Average this many consecutive points of every channel into one once the analyses are done
+    pub oversample: Option<usize>,
+    /// Upsample the points of every channel once the analyses and the oversampling are done
+    pub interpolation: Option<Interpolation>,
+    /// Add the points of every channel reduced to this many display columns
+    pub display_samples: Option<usize>,
+    /// Add the scales the channels and the trigger source would read best at, see `auto_scale_suggestion`
+    pub suggest_scales: bool,
+    /// Which of the frequency words of the measurements is the most significant
+    pub frequency_word_order: WordOrder,
+    /// Subtract the average voltage from the points of every channel before any analysis
+    pub remove_dc: bool,
+    /// Add human readable strings like `250 mV` next to the voltage measurements
+    pub human_measurements: bool,
+    /// Half width of the band around the final value of a step, in percent of the step, the signal has settled in
+    pub settling_tolerance_percent: f32,
+    /// Fraction of the larger value the measurements compared by the consistency checks and `compare_frequencies` may differ by
+    pub tolerance: f32,
+    /// Add the phase of channel 2 relative to channel 1 from their zero crossings
+    pub phase: bool,
+    /// Add the magnitude squared coherence between both channels
+    pub coherence: bool,
+    /// Add the transfer function from channel 1 as the input to channel 2 as the output
+    pub transfer_function: bool,
+    /// Fit a sine to channel 1
+    pub fit_sine: bool,
+    /// Add a histogram of the channel 1 voltages with this many bins
+    pub histogram_bins: Option<usize>,
+    /// Count the pulses of channel 1
+    pub pulse_count: Option<PulseCount>,
+    /// Add the points of every channel followed by this many autoregressive predictions
+    pub extrapolation: Option<Extrapolation>,
+    /// Analyse the pulses of channel 1 crossing this threshold
+    pub pulse_train_threshold_v: Option<f32>,
+    /// Add the glitches of channel 1
+    pub glitch_detection: Option<GlitchDetection>,
+    /// Add histograms of the channel 1 pulse widths
+    pub time_histogram: Option<TimeHistogramOptions>,
+    /// Add the points of both channels merged with this operation
+    pub merge_channels: Option<ChannelMerge>,
+    /// Add channel 1 minus channel 2 as a channel with its own scale and measurements
+    pub differential: bool,
+    /// Replace the points with their peak envelope decaying by this fraction per sample, before normalization
+    pub envelope_decay: Option<f32>,
+    /// Normalize the voltages of the points once every measurement has been computed
+    pub normalization: Option<Normalization>,
+    /// Add a spectrogram to every channel
+    pub spectrogram: Option<SpectrogramOptions>,
+    /// Add the period of every channel estimated from the peak of its FFT
+    pub fft_period: bool,
+    /// Add the frequency of every channel measured by the device, from its zero crossings and from its FFT
+    pub compare_frequencies: bool,
+    /// Add the waveform of every channel, see `detect_signal_type`
+    pub classify: bool,
+    /// Layout `parse_capture` parses the bytes with instead of the one detected from their size
+    pub channel_layout: Option<ChannelLayout>
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            min_autocorrelation: 0.5,
+            min_samples: None,
+            buffer: Buffer::Main,
+            annotate_divisions: false,
+            attenuation_override: None,
+            scale_override: None,
+            time_scale_override: None,
+            calibration: [Calibration::default(); 2],
+            reverse_samples: false,
+            roll_write_pointer: None,
+            time_origin_s: 0.0,
+            time_unit: None,
+            strict: false,
+            lenient: false,
+            diagnostics: false,
+            digitize: [None; 2],
+            parallel_decode: None,
+            oversample: None,
+            interpolation: None,
+            display_samples: None,
+            suggest_scales: false,
+            frequency_word_order: WordOrder::HighLow,
+            remove_dc: false,
+            human_measurements: false,
+            settling_tolerance_percent: 2.0,
+            tolerance: MEASUREMENT_TOLERANCE,
+            phase: false,
+            coherence: false,
+            transfer_function: false,
+            fit_sine: false,
+            histogram_bins: None,
+            pulse_count: None,
+            extrapolation: None,
+            pulse_train_threshold_v: None,
+            glitch_detection: None,
+            time_histogram: None,
+            merge_channels: None,
+            differential: false,
+            envelope_decay: None,
+            normalization: None,
+            spectrogram: None,
+            fft_period: false,
+            compare_frequencies: false,
+            classify: false,
+            channel_layout: None
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn builder() -> ParseOptionsBuilder {
+        ParseOptionsBuilder::new()
+    }
+}
+
+/// Builds `ParseOptions` starting from the defaults, every optional analysis is enabled by setting it
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptionsBuilder {
+    options: ParseOptions
+}
+
+impl ParseOptionsBuilder {
+    pub fn new() -> Self {
+        ParseOptionsBuilder { options: ParseOptions::default() }
+    }
+
+    pub fn min_autocorrelation(mut self, min_autocorrelation: f32) -> Self {
+        self.options.min_autocorrelation = min_autocorrelation;
+        self
+    }
+
+    pub fn min_samples(mut self, min_samples: usize) -> Self {
+        self.options.min_samples = Some(min_samples);
+        self
+    }
+
+    pub fn buffer(mut self, buffer: Buffer) -> Self {
+        self.options.buffer = buffer;
+        self
+    }
+
+    pub fn annotate_divisions(mut self, annotate_divisions: bool) -> Self {
+        self.options.annotate_divisions = annotate_divisions;
+        self
+    }
+
+    pub fn attenuation_override(mut self, attenuation_override: [Attenuation; 2]) -> Self {
+        self.options.attenuation_override = Some(attenuation_override);
+        self
+    }
+
+    pub fn scale_override(mut self, scale_override: [Scale<Volt>; 2]) -> Self {
+        self.options.scale_override = Some(scale_override);
+        self
+    }
+
+    pub fn time_scale_override(mut self, time_scale_override: Scale<Second>) -> Self {
+        self.options.time_scale_override = Some(time_scale_override);
+        self
+    }
+
+    /// Calibration of channel 1 or 2, other channels are an `UnknownChannel` error
+    pub fn calibration(mut self, channel: u8, calibration: Calibration) -> Result<Self, FnirsiError> {
+        match channel {
+            1 | 2 => self.options.calibration[channel as usize - 1] = calibration,
+            other => return Err(FnirsiError::UnknownChannel(other))
+        }
+        Ok(self)
+    }
+
+    pub fn reverse_samples(mut self, reverse_samples: bool) -> Self {
+        self.options.reverse_samples = reverse_samples;
+        self
+    }
+
+    pub fn roll_write_pointer(mut self, write_pointer: usize) -> Self {
+        self.options.roll_write_pointer = Some(write_pointer);
+        self
+    }
+
+    pub fn time_origin_s(mut self, time_origin_s: f32) -> Self {
+        self.options.time_origin_s = time_origin_s;
+        self
+    }
+
+    pub fn time_unit(mut self, time_unit: TimeUnitSelection) -> Self {
+        self.options.time_unit = Some(time_unit);
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.options.lenient = lenient;
+        self
+    }
+
+    pub fn diagnostics(mut self, diagnostics: bool) -> Self {
+        self.options.diagnostics = diagnostics;
+        self
+    }
+
+    /// Digitize channel 1 or 2, other channels are an `UnknownChannel` error
+    pub fn digitize(mut self, channel: u8, thresholds: LogicThresholds) -> Result<Self, FnirsiError> {
+        match channel {
+            1 | 2 => self.options.digitize[channel as usize - 1] = Some(thresholds),
+            other => return Err(FnirsiError::UnknownChannel(other))
+        }
+        Ok(self)
+    }
+
+    pub fn parallel_decode(mut self, parallel_decode: Vec<u8>) -> Self {
+        self.options.parallel_decode = Some(parallel_decode);
+        self
+    }
+
+    pub fn oversample(mut self, oversample: usize) -> Self {
+        self.options.oversample = Some(oversample);
+        self
+    }
+
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.options.interpolation = Some(interpolation);
+        self
+    }
+
+    pub fn display_samples(mut self, display_samples: usize) -> Self {
+        self.options.display_samples = Some(display_samples);
+        self
+    }
+
+    pub fn suggest_scales(mut self, suggest_scales: bool) -> Self {
+        self.options.suggest_scales = suggest_scales;
+        self
+    }
+
+    pub fn frequency_word_order(mut self, frequency_word_order: WordOrder) -> Self {
+        self.options.frequency_word_order = frequency_word_order;
+        self
+    }
+
+    pub fn remove_dc(mut self, remove_dc: bool) -> Self {
+        self.options.remove_dc = remove_dc;
+        self
+    }
+
+    pub fn human_measurements(mut self, human_measurements: bool) -> Self {
+        self.options.human_measurements = human_measurements;
+        self
+    }
+
+    pub fn settling_tolerance_percent(mut self, settling_tolerance_percent: f32) -> Self {
+        self.options.settling_tolerance_percent = settling_tolerance_percent;
+        self
+    }
+
+    pub fn tolerance(mut self, tolerance: f32) -> Self {
+        self.options.tolerance = tolerance;
+        self
+    }
+
+    pub fn phase(mut self, phase: bool) -> Self {
+        self.options.phase = phase;
+        self
+    }
+
+    pub fn coherence(mut self, coherence: bool) -> Self {
+        self.options.coherence = coherence;
+        self
+    }
+
+    pub fn transfer_function(mut self, transfer_function: bool) -> Self {
+        self.options.transfer_function = transfer_function;
+        self
+    }
+
+    pub fn fit_sine(mut self, fit_sine: bool) -> Self {
+        self.options.fit_sine = fit_sine;
+        self
+    }
+
+    pub fn histogram_bins(mut self, histogram_bins: usize) -> Self {
+        self.options.histogram_bins = Some(histogram_bins);
+        self
+    }
+
+    pub fn pulse_count(mut self, pulse_count: PulseCount) -> Self {
+        self.options.pulse_count = Some(pulse_count);
+        self
+    }
+
+    pub fn extrapolation(mut self, extrapolation: Extrapolation) -> Self {
+        self.options.extrapolation = Some(extrapolation);
+        self
+    }
+
+    pub fn pulse_train_threshold_v(mut self, pulse_train_threshold_v: f32) -> Self {
+        self.options.pulse_train_threshold_v = Some(pulse_train_threshold_v);
+        self
+    }
+
+    pub fn glitch_detection(mut self, glitch_detection: GlitchDetection) -> Self {
+        self.options.glitch_detection = Some(glitch_detection);
+        self
+    }
+
+    pub fn time_histogram(mut self, time_histogram: TimeHistogramOptions) -> Self {
+        self.options.time_histogram = Some(time_histogram);
+        self
+    }
+
+    pub fn merge_channels(mut self, merge_channels: ChannelMerge) -> Self {
+        self.options.merge_channels = Some(merge_channels);
+        self
+    }
+
+    pub fn differential(mut self, differential: bool) -> Self {
+        self.options.differential = differential;
+        self
+    }
+
+    pub fn envelope_decay(mut self, envelope_decay: f32) -> Self {
+        self.options.envelope_decay = Some(envelope_decay);
+        self
+    }
+
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.options.normalization = Some(normalization);
+        self
+    }
+
+    pub fn spectrogram(mut self, spectrogram: SpectrogramOptions) -> Self {
+        self.options.spectrogram = Some(spectrogram);
+        self
+    }
+
+    pub fn fft_period(mut self, fft_period: bool) -> Self {
+        self.options.fft_period = fft_period;
+        self
+    }
+
+    pub fn compare_frequencies(mut self, compare_frequencies: bool) -> Self {
+        self.options.compare_frequencies = compare_frequencies;
+        self
+    }
+
+    pub fn classify(mut self, classify: bool) -> Self {
+        self.options.classify = classify;
+        self
+    }
+
+    pub fn channel_layout(mut self, channel_layout: ChannelLayout) -> Self {
+        self.options.channel_layout = Some(channel_layout);
+        self
+    }
+
+    pub fn build(self) -> ParseOptions {
+        self.options
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpectrogramOptions {
+    /// Samples per FFT
+    pub window_size: usize,
+    /// Samples between the starts of consecutive windows
+    pub hop_size: usize
+}
+
+impl Default for SpectrogramOptions {
+    /// Non overlapping windows keep the output small
+    fn default() -> Self {
+        SpectrogramOptions { window_size: 128, hop_size: 128 }
+    }
+}
+
+/// Correction of a channel drifting from the reference voltages, `v * gain + offset_v`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Calibration {
+    pub gain: f32,
+    pub offset_v: f32
+}
+
+impl Default for Calibration {
+    /// Leaves the voltages untouched
+    fn default() -> Self {
+        Calibration { gain: 1.0, offset_v: 0.0 }
+    }
+}
+
+/// Calibration of the channels as stored in a toml file, channels without a table are left uncalibrated
+#[derive(Debug, Deserialize)]
+struct CalibrationFile {
+    ch1: Option<Calibration>,
+    ch2: Option<Calibration>
+}
+
+/// Schmitt trigger thresholds, the logic level goes high above `high_v` and low below `low_v`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogicThresholds {
+    pub high_v: f32,
+    pub low_v: f32
+}
+
+#[derive(Debug, Clone)]
+pub struct PulseCount {
+    pub threshold_v: f32,
+    /// Width of the hysteresis band centered on the threshold
+    pub hysteresis_v: f32,
+    /// Count rising edges when true and falling ones when false
+    pub active_high: bool
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Map the lowest voltage to -1 and the highest to 1
+    MinMax,
+    /// Map the top and bottom of the screen to 1 and -1
+    Scale
+}
+
+/// Unit of the times of the points
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TimeUnit {
+    #[serde(rename = "s")]
+    Seconds,
+    #[serde(rename = "ms")]
+    Milliseconds,
+    #[serde(rename = "us")]
+    Microseconds,
+    #[serde(rename = "ns")]
+    Nanoseconds
+}
+
+impl TimeUnit {
+    /// The unit of the prefix of a time scale, `ns` for `20ns/div` and `s` for `1s/div`
+    pub fn for_scale(scale: &Scale<Second>) -> Self {
+        match scale.scale {
+            exponent if exponent <= -9 => TimeUnit::Nanoseconds,
+            exponent if exponent <= -6 => TimeUnit::Microseconds,
+            exponent if exponent <= -3 => TimeUnit::Milliseconds,
+            _ => TimeUnit::Seconds
+        }
+    }
+
+    pub fn per_second(&self) -> f32 {
+        match self {
+            TimeUnit::Seconds => 1.0,
+            TimeUnit::Milliseconds => 1e3,
+            TimeUnit::Microseconds => 1e6,
+            TimeUnit::Nanoseconds => 1e9
+        }
+    }
+
+    /// The symbol of the unit, as it is named in the output
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TimeUnit::Seconds => "s",
+            TimeUnit::Milliseconds => "ms",
+            TimeUnit::Microseconds => "us",
+            TimeUnit::Nanoseconds => "ns"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnitSelection {
+    Fixed(TimeUnit),
+    /// The unit of the time scale of the capture
+    Auto
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolationMethod {
+    Linear,
+    Cubic
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interpolation {
+    pub method: InterpolationMethod,
+    /// Points per original point
+    pub factor: usize
+}
+
+#[derive(Debug, Clone)]
+pub struct Extrapolation {
+    pub num_extra_samples: usize,
+    /// Previous samples every prediction is a combination of
+    pub order: usize
+}
+
+#[derive(Debug, Clone)]
+pub struct GlitchDetection {
+    /// Longest excursion across the threshold still counted as a glitch
+    pub max_width_samples: usize,
+    pub threshold_v: f32
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeHistogramOptions {
+    /// Voltage the pulses start and end crossing
+    pub threshold_v: f32,
+    pub num_bins: usize
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Buffer {
+    /// The `CHANNEL_SAMPLES` samples of `channel11` and `channel21`
+    Main,
+    /// The `ZOOM_SAMPLES` samples of `channel12` and `channel22`
+    Zoom
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelMerge {
+    /// Channel 1 minus channel 2
+    Sub,
+    /// Channel 1 plus channel 2
+    Add
+}
+
+/// Reads only the header, the sample buffers after it are never touched
+pub fn parse_header(mut reader: impl Read + Seek) -> Result<Header, FnirsiError> {
+    Ok(reader.read_le()?)
+}
+
+/// Removes a leading UTF-8 or UTF-16 byte order mark left by text editors.
+/// Offsets within the file are absolute so the mark has to go before parsing.
+/// The first header bytes are unknown padding that could look like a mark, so only call this on captures known to be mangled.
+pub fn strip_bom(bytes: &[u8]) -> &[u8] {
+    [&[0xEF, 0xBB, 0xBF][..], &[0xFF, 0xFE], &[0xFE, 0xFF]].iter()
+        .find_map(|bom| bytes.strip_prefix(*bom))
+        .unwrap_or(bytes)
+}
+
+/// Everything the analyses of a capture depend on
+#[derive(Debug)]
+pub struct CaptureContext {
+    pub file: File,
+    pub model: FnirsiModel,
+    pub options: ParseOptions,
+    /// Size of the file the capture was read from, when it came from one
+    pub file_size: Option<usize>,
+    /// Bytes skipped before the capture, like a byte order mark
+    pub skipped_bytes: usize
+}
+
+impl CaptureContext {
+    pub fn new(file: File, options: ParseOptions) -> Self {
+        CaptureContext { file, model: FnirsiModel::Fnirsi1013D, options, file_size: None, skipped_bytes: 0 }
+    }
+
+    /// Samples of channel 1 or 2 in the buffer selected by the options
+    pub fn samples(&self, channel: u8) -> &[u16] {
+        match (self.options.buffer, channel) {
+            (Buffer::Main, 1) => &self.file.channel11,
+            (Buffer::Main, _) => &self.file.channel21,
+            (Buffer::Zoom, 1) => &self.file.channel12,
+            (Buffer::Zoom, _) => &self.file.channel22
+        }
+    }
+
+    /// The time scale override or else the one of the header, an `InvalidTimeScale` error for an index missing from the table
+    pub fn time_scale(&self) -> Result<Scale<Second>, FnirsiError> {
+        if let Some(time_scale) = self.options.time_scale_override {
+            return Ok(time_scale);
+        }
+
+        let time_scale = self.file.header.time_scale.try_into()?;
+        log::debug!("time_scale: {:x} = {}", self.file.header.time_scale, time_scale);
+        Ok(time_scale)
+    }
+}
+
+/// Checks every header field `parse_data` converts holds a known value, fields replaced by an override are skipped
+pub fn validate_header(context: &CaptureContext) -> Result<(), FnirsiError> {
+    fn check<T: TryFrom<u16>>(field: &'static str, value: u16) -> Result<(), FnirsiError> {
+        T::try_from(value).map(|_| ()).map_err(|_| FnirsiError::InvalidHeaderValue { field, value })
+    }
+
+    let (header, options) = (&context.file.header, &context.options);
+
+    if options.scale_override.is_none() {
+        check::<Scale<Volt>>("channel1_scale", header.channel1_scale)?;
+        check::<Scale<Volt>>("channel2_scale", header.channel2_scale)?;
+    }
+    if options.attenuation_override.is_none() && !options.lenient {
+        for (channel, value) in [(1, header.channel1_probe), (2, header.channel2_probe)] {
+            Attenuation::try_from(value).map_err(|_| FnirsiError::UnknownAttenuation { channel, value })?;
+        }
+    }
+    if options.time_scale_override.is_none() {
+        check::<Scale<Second>>("time_scale", header.time_scale)?;
+    }
+
+    check::<Coupling>("channel1_coupling", header.channel1_coupling)?;
+    check::<Coupling>("channel2_coupling", header.channel2_coupling)?;
+    check::<TriggerType>("trigger_type", header.trigger_type)?;
+    check::<TriggerEdge>("trigger_edge", header.trigger_edge)?;
+    check::<TriggerChannel>("trigger_channel", header.trigger_channel)?;
+    check::<Trigger50>("trigger_50", header.trigger_50)
+}
+
+/// Parses a capture and turns it into `Data` in one go, for embedders that don't need the `File` in between
+pub fn parse_capture(bytes: &[u8], options: ParseOptions) -> Result<Data, FnirsiError> {
+    let layout = options.channel_layout.unwrap_or_else(|| ChannelLayout::detect(bytes));
+    parse_data(&CaptureContext::new(parse_file_with_layout(bytes, layout)?, options))
+}
+
+/// A header field whose value is possible but unlikely to come from a working scope
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsistencyWarning {
+    pub field: String,
+    pub value: u16,
+    pub message: String
+}
+
+/// Cross-checks header fields whose values are known but don't make sense together or on the scope.
+/// The header has no field saying whether a channel is enabled, so a trigger on a disabled channel can't be caught
+pub fn verify_header_consistency(header: &Header) -> Vec<ConsistencyWarning> {
+    let mut warnings = Vec::new();
+    let mut warn = |field: &str, value: u16, message: String| warnings.push(ConsistencyWarning { field: field.to_string(), value, message });
+
+    for (field, offset) in [("channel1_offset", header.channel1_offset), ("channel2_offset", header.channel2_offset)] {
+        if offset > ADC_MAX {
+            warn(field, offset, format!("outside of the ADC range 0..={}", ADC_MAX));
+        }
+    }
+
+    for (field, brightness) in [("screen_brightness", header.screen_brightness), ("grid_brightness", header.grid_brightness)] {
+        if brightness > MAX_BRIGHTNESS {
+            warn(field, brightness, format!("brighter than the maximum of {}", MAX_BRIGHTNESS));
+        }
+    }
+
+    // Both traces centered is the default, the same offset anywhere else is more likely a copied field
+    if header.channel1_offset == header.channel2_offset && header.channel1_offset != ADC_MIDPOINT {
+        warn("channel2_offset", header.channel2_offset, "the same as channel1_offset away from the center of the screen".to_string());
+    }
+
+    warnings
+}
+
+/// Number of samples beyond `ADC_MAX`, which only a corrupt capture holds
+pub fn samples_out_of_range(samples: &[u16]) -> usize {
+    samples.iter().filter(|sample| **sample > ADC_MAX).count()
+}
+
+/// Channel offset centering the samples at 0 V, the one minimizing the mean absolute voltage of the points.
+/// That is the median sample whatever the voltage scale, `ADC_MIDPOINT` without samples
+pub fn compute_optimal_offset(samples: &[u16]) -> u16 {
+    if samples.is_empty() {
+        return ADC_MIDPOINT;
+    }
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    sorted[(sorted.len() - 1) / 2]
+}
+
+/// Whether a frequency can be measured in the window of a capture
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum ValidationResult {
+    Ok,
+    /// Above the nyquist limit of the sample rate
+    TooHighForWindow { max_measurable: f64, claimed: f64 },
+    /// Too slow for 2 complete cycles to fit the window
+    TooLowForWindow { min_measurable: f64, claimed: f64 }
+}
+
+/// Checks that a frequency claimed by the measurements can be measured in a main buffer captured with `time_scale`,
+/// between 2 complete cycles in the window and the nyquist limit. A zero frequency claims nothing and is `Ok`
+pub fn validate_time_scale_vs_frequency(time_scale: &Scale<Second>, frequency_hz: u32) -> ValidationResult {
+    let sample_rate_hz = sample_rate_hz(time_scale) as f64;
+    let window_s = CHANNEL_SAMPLES as f64 / sample_rate_hz;
+    let (claimed, min_measurable, max_measurable) = (frequency_hz as f64, 2.0 / window_s, sample_rate_hz / 2.0);
+
+    match frequency_hz {
+        0 => ValidationResult::Ok,
+        _ if claimed > max_measurable => ValidationResult::TooHighForWindow { max_measurable, claimed },
+        _ if claimed < min_measurable => ValidationResult::TooLowForWindow { min_measurable, claimed },
+        _ => ValidationResult::Ok
+    }
+}
+
+/// Smallest steps the measurements of a capture can resolve
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MeasurementUncertainty {
+    /// Voltage of one ADC count
+    pub voltage_resolution_mv: f64,
+    /// Width of a bin of the spectrum of the samples, infinite without samples
+    pub frequency_resolution_hz: f64,
+    /// Time between 2 samples
+    pub time_resolution_ns: f64
+}
+
+/// Precision limits of `num_samples` samples captured at `scale` and `time_scale`, `scale` is the one at the probe tip
+pub fn compute_uncertainty(scale: &Scale<Volt>, time_scale: &Scale<Second>, num_samples: usize) -> MeasurementUncertainty {
+    // In f64 from the exponents, `get_scale` rounds to f32 which shows in the json as 4.000000059604645 mV.
+    // Like the points, `DIVISION_POINTS` samples make a division of both scales
+    let per_division = |value: f32, exponent: i32| value as f64 * 10f64.powi(exponent) / DIVISION_POINTS as f64;
+    let sample_interval_s = per_division(time_scale.value, time_scale.scale);
+
+    MeasurementUncertainty {
+        voltage_resolution_mv: per_division(scale.value, scale.scale + 3),
+        frequency_resolution_hz: 1.0 / (sample_interval_s * num_samples as f64),
+        time_resolution_ns: sample_interval_s * 1e9
+    }
+}
+
+/// Checks of the header of a channel, the offset next to the one its samples suggest, they differ for signals not centered on 0 V
+#[derive(Debug, Serialize)]
+pub struct ChannelValidation {
+    pub stored_offset: u16,
+    pub computed_offset: u16,
+    pub frequency: ValidationResult,
+    pub uncertainty: MeasurementUncertainty
+}
+
+/// Checks of the header of a capture against its samples
+#[derive(Debug, Serialize)]
+pub struct Validation {
+    pub channel1: ChannelValidation,
+    pub channel2: ChannelValidation
+}
+
+/// Validates the header of a capture against the samples of the buffer selected by the options,
+/// failing like `parse_data` on header values that can't be converted
+pub fn validate_capture(context: &CaptureContext) -> Result<Validation, FnirsiError> {
+    validate_header(context)?;
+
+    let (header, options) = (&context.file.header, &context.options);
+    let time_scale = context.time_scale()?;
+    let channel = |channel: u8, raw_scale: u16, probe: u16, stored_offset, measurements: &Measurements| -> Result<ChannelValidation, FnirsiError> {
+        let index = channel as usize - 1;
+        let scale = match options.scale_override {
+            Some(scales) => scales[index],
+            None => raw_scale.try_into()?
+        };
+        let attenuation = options.attenuation_override.map(|attenuations| attenuations[index])
+            .unwrap_or_else(|| probe.try_into().unwrap_or(Attenuation::OneX));
+
+        Ok(ChannelValidation {
+            stored_offset,
+            computed_offset: compute_optimal_offset(context.samples(channel)),
+            frequency: validate_time_scale_vs_frequency(&time_scale, parse_frequency_ordered(measurements.frequency_high, measurements.frequency_low, options.frequency_word_order)),
+            uncertainty: compute_uncertainty(&scale.with_attenuation(attenuation), &time_scale, context.samples(channel).len())
+        })
+    };
+
+    Ok(Validation {
+        channel1: channel(1, header.channel1_scale, header.channel1_probe, header.channel1_offset, &header.channel1_measurements)?,
+        channel2: channel(2, header.channel2_scale, header.channel2_probe, header.channel2_offset, &header.channel2_measurements)?
+    })
+}
+
+pub fn parse_data(context: &CaptureContext) -> Result<Data, FnirsiError> {
+    validate_header(context)?;
+
+    let header_warnings = verify_header_consistency(&context.file.header);
+
+    for warning in header_warnings.iter().cloned() {
+        if context.options.strict {
+            return Err(FnirsiError::InconsistentHeader(warning));
+        }
+        log::warn!("The header field {} has the suspicious value {}: {}", warning.field, warning.value, warning.message);
+    }
+
+    for channel in [1, 2] {
+        let count = samples_out_of_range(context.samples(channel));
+
+        if count > 0 {
+            if context.options.strict {
+                return Err(FnirsiError::SamplesOutOfRange { channel, count });
+            }
+            log::warn!("Channel {} has {} samples outside of the ADC range 0..={}", channel, count, ADC_MAX);
+        }
+    }
+
+    let (file, options) = (&context.file, &context.options);
+    let layout = context.model.layout();
+    let min_samples = options.min_samples.unwrap_or(match options.buffer {
+        Buffer::Main => layout.channel_samples,
+        Buffer::Zoom => layout.zoom_samples
+    });
+
+    for channel in [1, 2] {
+        let samples = context.samples(channel).len();
+
+        if samples < min_samples {
+            return Err(FnirsiError::TooFewSamples { channel, samples, min_samples });
+        }
+    }
+
+    let time_scale = context.time_scale()?;
+    let channel1 = build_channel(context, 1, &time_scale)?;
+    let channel2 = build_channel(context, 2, &time_scale)?;
+    let channel1_pulse_count = options.pulse_count.as_ref()
+        .map(|pulse_count| count_pulses(&channel1.points, pulse_count.threshold_v, pulse_count.hysteresis_v, pulse_count.active_high));
+
+    let phase_deg = if options.phase { phase_from_zero_crossings(&channel1.points, &channel2.points) } else { None };
+    let channel1_sine_fit = if options.fit_sine { fit_sine(&channel1.points) } else { None };
+    let (channel1_high_pulse_histogram, channel1_low_pulse_histogram) = options.time_histogram.as_ref()
+        .map(|histogram_options| time_histogram(&channel1.points, histogram_options.threshold_v, histogram_options.num_bins))
+        .unzip();
+    let channel1_pulse_train = options.pulse_train_threshold_v.map(|threshold_v| pulse_train_analysis(&channel1.points, threshold_v));
+    let channel1_glitches = options.glitch_detection.as_ref()
+        .map(|glitch_detection| find_glitches(&channel1.points, glitch_detection.max_width_samples, glitch_detection.threshold_v));
+    let channel1_histogram = options.histogram_bins.map(|num_bins| voltage_histogram(&channel1.points, num_bins));
+    let coherence = options.coherence.then(|| coherence(&channel1.points, &channel2.points, sample_rate_hz(&time_scale) as f64));
+    let transfer_function = options.transfer_function.then(|| frequency_response(&channel1.points, &channel2.points, sample_rate_hz(&time_scale) as f64));
+
+    let trigger_channel = file.header.trigger_channel.try_into()?;
+    let trigger_50 = file.header.trigger_50.try_into()?;
+    let digital = |points: &[Point], thresholds: Option<LogicThresholds>| thresholds.map(|thresholds| digitize(points, thresholds.high_v, thresholds.low_v));
+    let (channel1_digital, channel2_digital) = (digital(&channel1.points, options.digitize[0]), digital(&channel2.points, options.digitize[1]));
+    let parallel_words = options.parallel_decode.as_ref().map(|channels| {
+        channels.iter().map(|channel| match channel {
+            1 => channel1_digital.as_deref(),
+            2 => channel2_digital.as_deref(),
+            _ => None
+        }.ok_or(FnirsiError::NotDigitized(*channel)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|bits| reconstruct_digital_word(&bits))
+    }).transpose()?;
+    let level_v = match (&trigger_50, &trigger_channel) {
+        (Trigger50::On, TriggerChannel::Channel1) => midpoint_v(&channel1.points),
+        (Trigger50::On, TriggerChannel::Channel2) => midpoint_v(&channel2.points),
+        (Trigger50::Off, _) => None
+    };
+
+    let mut merged_points = options.merge_channels
+        .map(|merge| merge_channels(&channel1.points, &channel2.points, merge))
+        .transpose()?;
+    let mut differential = options.differential.then(|| differential_channel(&channel1, &channel2, layout.vertical_divisions as f32)).transpose()?;
+    let warnings = warnings(context, &header_warnings, &time_scale, [&channel1, &channel2]);
+    let (suggested_ch1_scale, suggested_ch2_scale, suggested_time_scale) = if options.suggest_scales {
+        // The measurements are at the probe tip and the suggestions at the scope input
+        let suggest = |channel: &Channel| {
+            let attenuation = channel.probe.attenuation;
+            let at_input = suggest_voltage_scale(channel.measurements.vpp / attenuation.multiplier());
+
+            format!("{}/div", at_input.with_attenuation(attenuation))
+        };
+        let trigger_source = if channel2.is_trigger_source { &channel2 } else { &channel1 };
+
+        (Some(suggest(&channel1)), Some(suggest(&channel2)), Some(format!("{}/div", auto_scale_suggestion(&trigger_source.measurements).1)))
+    } else {
+        (None, None, None)
+    };
+    let diagnostics = options.diagnostics.then(|| {
+        let mut diagnostics = diagnostics(context, header_warnings.len());
+
+        for (channel, measurements) in [(1, &channel1.measurements), (2, &channel2.measurements)] {
+            if measurements.frequency_estimated_hz.is_some() {
+                diagnostics.fallbacks.push(format!("channel {} frequency was estimated from the samples", channel));
+            }
+        }
+        diagnostics
+    });
+
+    let (mut channel1, mut channel2) = (channel1, channel2);
+
+    for channel in IntoIterator::into_iter([&mut channel1, &mut channel2]).chain(differential.as_mut()) {
+        if let Some(decay_rate) = options.envelope_decay {
+            channel.points = envelope_detection(&channel.points, decay_rate);
+        }
+
+        channel.points = match options.normalization {
+            Some(Normalization::MinMax) => normalize_points(&channel.points),
+            Some(Normalization::Scale) => normalize_points_symmetric(&channel.points, channel.effective_scale.get_scale() * layout.vertical_divisions as f32 / 2.0),
+            None => continue
+        };
+    }
+
+    // The analyses above rely on the sample rate of the capture so the averaging comes after them
+    if let Some(factor) = options.oversample {
+        for channel in IntoIterator::into_iter([&mut channel1, &mut channel2]).chain(differential.as_mut()) {
+            channel.points = oversample_average(&channel.points, factor);
+        }
+    }
+
+    if let Some(interpolation) = options.interpolation {
+        for channel in IntoIterator::into_iter([&mut channel1, &mut channel2]).chain(differential.as_mut()) {
+            channel.points = interpolate(&channel.points, interpolation.method, interpolation.factor);
+        }
+    }
+
+    // Last so every analysis still sees the capture starting at 0
+    if options.time_origin_s != 0.0 {
+        for channel in IntoIterator::into_iter([&mut channel1, &mut channel2]).chain(differential.as_mut()) {
+            channel.points.iter_mut().for_each(|point| point.time += options.time_origin_s);
+            channel.extrapolated_points.iter_mut().flatten().for_each(|point| point.time += options.time_origin_s);
+        }
+        merged_points.iter_mut().flatten().for_each(|point| point.time += options.time_origin_s);
+    }
+
+    // After the time origin so the windows start at the shifted times, before the time unit which they don't follow
+    if let Some(output_samples) = options.display_samples {
+        for channel in IntoIterator::into_iter([&mut channel1, &mut channel2]).chain(differential.as_mut()) {
+            channel.display_points = Some(downsample_minmax(&channel.points, output_samples));
+        }
+    }
+
+    let time_unit = options.time_unit.map(|selection| match selection {
+        TimeUnitSelection::Fixed(unit) => unit,
+        TimeUnitSelection::Auto => TimeUnit::for_scale(&time_scale)
+    });
+
+    if let Some(per_second) = time_unit.map(|unit| unit.per_second()).filter(|per_second| *per_second != 1.0) {
+        for channel in IntoIterator::into_iter([&mut channel1, &mut channel2]).chain(differential.as_mut()) {
+            channel.points.iter_mut().for_each(|point| point.time *= per_second);
+            channel.extrapolated_points.iter_mut().flatten().for_each(|point| point.time *= per_second);
+        }
+        merged_points.iter_mut().flatten().for_each(|point| point.time *= per_second);
+    }
+
+    if options.annotate_divisions {
+        // The oversampling and the interpolation change how many points a division spans
+        let points_per_division = DIVISION_POINTS as f64 / options.oversample.unwrap_or(1).max(1) as f64
+            * options.interpolation.map_or(1, |interpolation| interpolation.factor) as f64;
+
+        for channel in IntoIterator::into_iter([&mut channel1, &mut channel2]).chain(differential.as_mut()) {
+            channel.division_markers = Some(division_markers(channel.points.len(), points_per_division));
+        }
+    }
+
+    Ok(Data {
+        sequence_index: None,
+        trigger: Trigger {
+            trigger_type: file.header.trigger_type.try_into()?,
+            edge: file.header.trigger_edge.try_into()?,
+            channel: trigger_channel,
+            trigger_50,
+            level_v
+        },
+        time_scale,
+        time_unit,
+        quality_score: channel1.quality.score.min(channel2.quality.score),
+        channel1,
+        channel2,
+        channel1_pulse_count,
+        phase_deg,
+        channel1_sine_fit,
+        coherence,
+        transfer_function,
+        channel1_histogram,
+        channel1_high_pulse_histogram,
+        channel1_low_pulse_histogram,
+        channel1_pulse_train,
+        channel1_glitches,
+        merged_points,
+        differential,
+        channel1_digital,
+        channel2_digital,
+        parallel_words,
+        diagnostics,
+        suggested_ch1_scale,
+        suggested_ch2_scale,
+        suggested_time_scale,
+        warnings
+    })
+}
+
+/// The warnings of a parse as `code: message`, before the points are post processed
+fn warnings(context: &CaptureContext, header_warnings: &[ConsistencyWarning], time_scale: &Scale<Second>, channels: [&Channel; 2]) -> Vec<String> {
+    let (header, options) = (&context.file.header, &context.options);
+    let mut warnings: Vec<String> = header_warnings.iter()
+        .map(|warning| format!("suspicious_header: the header field {} has the suspicious value {}: {}", warning.field, warning.value, warning.message))
+        .collect();
+
+    for (index, channel) in channels.iter().enumerate() {
+        let number = index + 1;
+        let (raw_scale, probe) = match number {
+            1 => (header.channel1_scale, header.channel1_probe),
+            _ => (header.channel2_scale, header.channel2_probe)
+        };
+
+        if options.scale_override.is_none() && PROBE_SCALES.get(raw_scale as usize).is_some_and(|entry| !entry.is_valid) {
+            warnings.push(format!("placeholder_scale: channel {} scale index {} is a placeholder", number, raw_scale));
+        }
+        if options.attenuation_override.is_none() && Attenuation::try_from(probe).is_err() {
+            warnings.push(format!("unknown_attenuation: channel {} probe value {} was read as 1x", number, probe));
+        }
+        // The device reports 0 Hz for the signals it found no frequency in
+        if channel.measurements.frequency != 0 && !is_plausible_frequency(channel.measurements.frequency, time_scale) {
+            warnings.push(format!("implausible_frequency: channel {} frequency {} Hz can't be measured at {}/div", number, channel.measurements.frequency, time_scale));
+        }
+        let measurements = &channel.measurements;
+        if !vpp_consistent(measurements, options.tolerance) {
+            warnings.push(format!(
+                "vpp_mismatch: channel {} vpp {} differs from vmax - vmin {} by more than {}%",
+                number, format_voltage_display(measurements.vpp), format_voltage_display(measurements.vmax - measurements.vmin), options.tolerance * 100.0
+            ));
+        }
+        if !period_consistent(measurements, options.tolerance) {
+            warnings.push(format!(
+                "period_mismatch: channel {} cycle time {} us differs from the period of its frequency {} Hz by more than {}%",
+                number, cycle_time_us(measurements), measurements.frequency, options.tolerance * 100.0
+            ));
+        }
+        if !duty_consistent(measurements, options.tolerance) {
+            warnings.push(format!(
+                "duty_mismatch: channel {} duty cycles {}% and {}% differ from a sum of 100% by more than {}%",
+                number, measurements.duty_plus_percentage, measurements.duty_minus_percentage, options.tolerance * 100.0
+            ));
+        }
+        let out_of_range = samples_out_of_range(context.samples(number as u8));
+        if out_of_range > 0 {
+            warnings.push(format!("samples_out_of_range: channel {} has {} samples outside of the ADC range 0..={}", number, out_of_range, ADC_MAX));
+        }
+        if channel.quality.clipping_fraction > 0.0 {
+            warnings.push(format!("clipping: channel {} has {:.1}% of its points at the edge of the screen", number, channel.quality.clipping_fraction * 100.0));
+        }
+    }
+
+    warnings
+}
+
+fn diagnostics(context: &CaptureContext, header_warnings: usize) -> Diagnostics {
+    let (file, options) = (&context.file, &context.options);
+    let samples = [&file.channel11, &file.channel21, &file.channel12, &file.channel22, &file.channel31, &file.channel41, &file.channel32, &file.channel42]
+        .iter()
+        .map(|buffer| buffer.len())
+        .sum::<usize>();
+    let mut fallbacks = Vec::new();
+
+    if context.skipped_bytes > 0 {
+        fallbacks.push(format!("skipped {} bytes before the capture", context.skipped_bytes));
+    }
+    if options.scale_override.is_none() {
+        for (channel, index) in [(1, file.header.channel1_scale), (2, file.header.channel2_scale)] {
+            if PROBE_SCALES.get(index as usize).is_some_and(|entry| !entry.is_valid) {
+                fallbacks.push(format!("channel {} scale index {} is a placeholder", channel, index));
+            }
+        }
+    } else {
+        fallbacks.push("the channel scales were overridden".to_string());
+    }
+    if options.attenuation_override.is_some() {
+        fallbacks.push("the channel attenuations were overridden".to_string());
+    } else {
+        for (channel, value) in [(1, file.header.channel1_probe), (2, file.header.channel2_probe)] {
+            if Attenuation::try_from(value).is_err() {
+                fallbacks.push(format!("channel {} probe value {} was read as 1x", channel, value));
+            }
+        }
+    }
+    match options.time_scale_override {
+        Some(_) => fallbacks.push("the time scale was overridden".to_string()),
+        None if TIME_SCALES.get(file.header.time_scale as usize).is_some_and(|entry| !entry.is_valid) =>
+            fallbacks.push(format!("time scale index {} is a placeholder", file.header.time_scale)),
+        None => {}
+    }
+    if header_warnings > 0 {
+        fallbacks.push(format!("suspicious header values: {}", header_warnings));
+    }
+
+    Diagnostics {
+        channel1_samples: file.channel11.len(),
+        channel2_samples: file.channel21.len(),
+        channel1_zoom_samples: file.channel12.len(),
+        channel2_zoom_samples: file.channel22.len(),
+        // Everything before the sample buffers is read or skipped by the header
+        bytes_read: context.skipped_bytes + context.model.layout().channel_data_offset as usize + samples * std::mem::size_of::<u16>(),
+        file_size: context.file_size,
+        fallbacks
+    }
+}
+
+/// Serializes `value` as json with the keys of every object sorted, so the same value always makes the same bytes
+/// whatever order its fields are declared or inserted in
+pub fn to_canonical_json<T: Serialize>(value: &T) -> Result<String, FnirsiError> {
+    // Through the json text rather than `serde_json::to_value`, which widens the f32 values to f64 like `json_f32` describes
+    let value: serde_json::Value = serde_json::from_str(&serde_json::to_string(value)?)?;
+
+    Ok(serde_json::to_string(&sort_json_keys(value))?)
+}
+
+/// `data` as json with the points of every channel as parallel `times` and `voltages` arrays instead of `{time, voltage}`
+/// objects, which is far more compact and faster to load into numpy or js
+pub fn to_columnar_json(data: &Data) -> Result<serde_json::Value, FnirsiError> {
+    // Through the json text like `to_canonical_json`
+    let mut value: serde_json::Value = serde_json::from_str(&serde_json::to_string(data)?)?;
+
+    for name in ["channel1", "channel2", "differential"] {
+        let channel = match value.get_mut(name).and_then(serde_json::Value::as_object_mut) {
+            Some(channel) => channel,
+            None => continue
+        };
+        let points = match channel.remove("points") {
+            Some(serde_json::Value::Array(points)) => points,
+            _ => continue
+        };
+        let column = |key: &str| serde_json::Value::Array(points.iter().map(|point| point[key].clone()).collect());
+
+        channel.insert("times".to_string(), column("time"));
+        channel.insert("voltages".to_string(), column("voltage"));
+    }
+
+    Ok(value)
+}
+
+fn sort_json_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            serde_json::Value::Object(entries.into_iter().map(|(key, value)| (key, sort_json_keys(value))).collect())
+        }
+        serde_json::Value::Array(values) => serde_json::Value::Array(values.into_iter().map(sort_json_keys).collect()),
+        other => other
+    }
+}
+
+/// The value as a json number, through its shortest decimal so `2e-6` doesn't become `1.9999999949504854e-6` as an `f64`
+fn json_f32(value: f32) -> serde_json::Value {
+    value.to_string().parse::<f64>().map(serde_json::Value::from).unwrap_or(serde_json::Value::Null)
+}
+
+/// The points of a channel as `[{"time_s": 0.0, "voltage_v": 1.23}, ...]`, a data frame for pandas `read_json(orient="records")`
+pub fn channel_to_dataframe_json(channel: &Channel) -> serde_json::Value {
+    channel.points.iter().map(|point| serde_json::json!({ "time_s": json_f32(point.time), "voltage_v": json_f32(point.voltage) })).collect()
+}
+
+/// The points of a channel as `{"time_s": [...], "voltage_v": [...]}`, more compact than the records for not repeating the keys
+pub fn channel_to_split_json(channel: &Channel) -> serde_json::Value {
+    serde_json::json!({
+        "time_s": channel.points.iter().map(|point| json_f32(point.time)).collect::<Vec<_>>(),
+        "voltage_v": channel.points.iter().map(|point| json_f32(point.voltage)).collect::<Vec<_>>()
+    })
+}
+
+/// The points of a channel as `{"time_s": {"0": 0.0, ...}, "voltage_v": {"0": 1.23, ...}}` keyed by their index,
+/// for pandas `read_json(orient="columns")`
+pub fn channel_to_columns_json(channel: &Channel) -> serde_json::Value {
+    let column = |value: fn(&Point) -> f32| channel.points.iter()
+        .enumerate()
+        .map(|(index, point)| (index.to_string(), json_f32(value(point))))
+        .collect::<serde_json::Map<_, _>>();
+
+    serde_json::json!({ "time_s": column(|point| point.time), "voltage_v": column(|point| point.voltage) })
+}
+
+pub fn write_messagepack(data: &Data, writer: &mut impl Write) -> Result<(), FnirsiError> {
+    Ok(rmp_serde::encode::write_named(writer, data)?)
+}
+
+pub fn write_cbor(data: &Data, writer: &mut impl Write) -> Result<(), FnirsiError> {
+    Ok(ciborium::ser::into_writer(data, writer)?)
+}
+
+/// A format `Data` can be written in, implement it to add formats to [`OutputFormat::Custom`]
+pub trait OutputFormatter: Send + Sync {
+    fn write(&self, data: &Data, writer: &mut dyn Write) -> Result<(), FnirsiError>;
+}
+
+/// The formats writing a single capture to a stream, new ones may be added so matches need a wildcard arm
+#[non_exhaustive]
+pub enum OutputFormat {
+    Json,
+    Messagepack,
+    Cbor,
+    /// A format implemented outside of this crate
+    Custom(Box<dyn OutputFormatter>)
+}
+
+impl OutputFormatter for OutputFormat {
+    fn write(&self, data: &Data, mut writer: &mut dyn Write) -> Result<(), FnirsiError> {
+        match self {
+            OutputFormat::Json => Ok(serde_json::to_writer(writer, data)?),
+            OutputFormat::Messagepack => write_messagepack(data, &mut writer),
+            OutputFormat::Cbor => write_cbor(data, &mut writer),
+            OutputFormat::Custom(formatter) => formatter.write(data, writer)
+        }
+    }
+}
+
+/// Writes channel 1 or 2 in the csv layout of the Tektronix TDS1000 and TDS2000 series, which many waveform tools import.
+/// Every row has six columns, the metadata label and value in the first two of the first rows and the time and voltage
+/// of a point in the fourth and fifth of every row.
+pub fn write_tektronix_csv(data: &Data, channel: u8, writer: &mut impl Write) -> Result<(), FnirsiError> {
+    let (source, channel) = match channel {
+        1 => ("CH1", &data.channel1),
+        2 => ("CH2", &data.channel2),
+        other => return Err(FnirsiError::UnknownChannel(other))
+    };
+    let sample_interval_s = 1.0 / sample_rate_hz(&data.time_scale) as f64;
+    let metadata = [
+        ("Record Length", format_scientific(channel.points.len() as f64, 6)),
+        ("Sample Interval", format_scientific(sample_interval_s, 6)),
+        // The position of the trigger within the capture isn't known
+        ("Trigger Point", format_scientific(0.0, 12)),
+        ("", String::new()),
+        ("", String::new()),
+        ("", String::new()),
+        ("Source", source.to_string()),
+        ("Vertical Units", "V".to_string()),
+        ("Vertical Scale", format_scientific(channel.effective_scale.get_scale() as f64, 2)),
+        // The points are relative to 0V already
+        ("Vertical Offset", format_scientific(0.0, 2)),
+        ("Horizontal Units", "s".to_string()),
+        ("Horizontal Scale", format_scientific(data.time_scale.get_scale() as f64, 2)),
+        ("Pt Fmt", "Y".to_string()),
+        ("Yzero", format_scientific(0.0, 2)),
+        ("Probe Atten", format_scientific(channel.probe.attenuation.multiplier() as f64, 2)),
+        ("Model Number", "FNIRSI 1013D".to_string()),
+        ("Serial Number", String::new()),
+        ("Firmware Version", String::new())
+    ];
+
+    for (index, point) in channel.points.iter().enumerate() {
+        let (label, value) = metadata.get(index).map(|(label, value)| (*label, value.as_str())).unwrap_or(("", ""));
+        writeln!(writer, "{},{},,{},{},", label, value, format_scientific(point.time as f64, 6), format_scientific(point.voltage as f64, 4))?;
+    }
+
+    Ok(())
+}
+
+/// Columns repeated for every channel of the measurements csv, prefixed with `ch1_` and `ch2_`
+const MEASUREMENTS_CSV_CHANNEL_COLUMNS: [&str; 15] = [
+    "coupling", "scale_v", "attenuation", "vmax", "vmin", "vavg", "vrms", "vpp", "vp",
+    "frequency", "frequency_estimated_hz", "duty_plus_percentage", "duty_minus_percentage", "crest_factor", "quality_score"
+];
+
+/// Writes the header row of the measurements csv, which has one row per capture
+pub fn write_measurements_csv_header(writer: &mut impl Write) -> Result<(), FnirsiError> {
+    let mut columns: Vec<String> = ["filename", "trigger_type", "trigger_edge", "trigger_channel", "trigger_50"].iter().map(|column| column.to_string()).collect();
+
+    for prefix in ["ch1", "ch2"] {
+        columns.extend(MEASUREMENTS_CSV_CHANNEL_COLUMNS.iter().map(|column| format!("{}_{}", prefix, column)));
+    }
+
+    Ok(writeln!(writer, "{}", columns.join(","))?)
+}
+
+/// Writes the trigger and the measurements of both channels of a capture as one row, unknown values are left empty
+pub fn write_measurements_csv_row(filename: &str, data: &Data, writer: &mut impl Write) -> Result<(), FnirsiError> {
+    let optional = |value: Option<f32>| value.map(|value| value.to_string()).unwrap_or_default();
+    let filename = if filename.contains(&[',', '"', '\n'][..]) { format!("\"{}\"", filename.replace('"', "\"\"")) } else { filename.to_string() };
+    let trigger = &data.trigger;
+    let mut values = vec![filename, format!("{:?}", trigger.trigger_type), format!("{:?}", trigger.edge), format!("{:?}", trigger.channel), format!("{:?}", trigger.trigger_50)];
+
+    for channel in [&data.channel1, &data.channel2] {
+        let measurements = &channel.measurements;
+
+        values.extend([
+            channel.probe.coupling.to_string(),
+            channel.effective_scale.get_scale().to_string(),
+            channel.probe.attenuation.multiplier().to_string(),
+            measurements.vmax.to_string(),
+            measurements.vmin.to_string(),
+            measurements.vavg.to_string(),
+            measurements.vrms.to_string(),
+            measurements.vpp.to_string(),
+            measurements.vp.to_string(),
+            measurements.frequency.to_string(),
+            optional(measurements.frequency_estimated_hz),
+            measurements.duty_plus_percentage.to_string(),
+            measurements.duty_minus_percentage.to_string(),
+            optional(measurements.crest_factor),
+            channel.quality.score.to_string()
+        ]);
+    }
+
+    Ok(writeln!(writer, "{}", values.join(","))?)
+}
+
+/// Formats `value` like `2.500000e+03`, with a sign and at least two digits in the exponent
+pub fn format_scientific(value: f64, decimals: usize) -> String {
+    let number = format!("{:.*e}", decimals, value);
+    let (mantissa, exponent) = number.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+
+    format!("{}e{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs())
+}
+
+/// Builds a record batch of the channel points with `time_s`, `ch1_v` and `ch2_v` columns
+/// and the scales and trigger type as schema metadata
+#[cfg(feature = "arrow")]
+fn points_record_batch(data: &Data) -> Result<arrow::record_batch::RecordBatch, FnirsiError> {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use arrow::array::{ArrayRef, Float64Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+
+    let metadata = HashMap::from([
+        ("time_scale".to_string(), data.time_scale.to_string()),
+        ("channel1_scale".to_string(), data.channel1.effective_scale.to_string()),
+        ("channel2_scale".to_string(), data.channel2.effective_scale.to_string()),
+        ("channel1_coupling".to_string(), data.channel1.probe.coupling.to_string()),
+        ("channel2_coupling".to_string(), data.channel2.probe.coupling.to_string()),
+        ("trigger_type".to_string(), format!("{:?}", data.trigger.trigger_type)),
+        ("trigger_edge".to_string(), format!("{:?}", data.trigger.edge)),
+        ("trigger_channel".to_string(), format!("{:?}", data.trigger.channel))
+    ]);
+    let schema = Arc::new(Schema::new_with_metadata(vec![
+        Field::new("time_s", DataType::Float64, false),
+        Field::new("ch1_v", DataType::Float64, false),
+        Field::new("ch2_v", DataType::Float64, false)
+    ], metadata));
+
+    let column = |points: &[Point], value: fn(&Point) -> f32| -> ArrayRef {
+        Arc::new(points.iter().map(|point| value(point) as f64).collect::<Float64Array>())
+    };
+
+    Ok(RecordBatch::try_new(schema, vec![
+        column(&data.channel1.points, |point| point.time),
+        column(&data.channel1.points, |point| point.voltage),
+        column(&data.channel2.points, |point| point.voltage)
+    ])?)
+}
+
+/// Writes the channel points as an Arrow IPC file
+#[cfg(feature = "arrow")]
+pub fn write_arrow_ipc(data: &Data, path: &Path) -> Result<(), FnirsiError> {
+    let batch = points_record_batch(data)?;
+    let mut writer = arrow::ipc::writer::FileWriter::try_new(std::fs::File::create(path)?, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "arrow"))]
+pub fn write_arrow_ipc(_data: &Data, _path: &Path) -> Result<(), FnirsiError> {
+    Err(FnirsiError::FeatureDisabled("arrow"))
+}
+
+/// Writes the channel points as a Feather v2 file, which is the Arrow IPC file format under the name pandas reads
+/// with `pd.read_feather`
+pub fn write_feather(data: &Data, path: &Path) -> Result<(), FnirsiError> {
+    write_arrow_ipc(data, path)
+}
+
+/// Writes the channel points as a Parquet file with the same columns as the arrow output
+#[cfg(feature = "parquet")]
+pub fn write_parquet(data: &Data, path: &Path) -> Result<(), FnirsiError> {
+    let batch = points_record_batch(data)?;
+    let mut writer = parquet::arrow::ArrowWriter::try_new(std::fs::File::create(path)?, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+pub fn write_parquet(_data: &Data, _path: &Path) -> Result<(), FnirsiError> {
+    Err(FnirsiError::FeatureDisabled("parquet"))
+}
+
+pub const SCREEN_WIDTH: usize = 320;
+pub const SCREEN_HEIGHT: usize = 240;
+/// Height of the waveform area, the rest of the screen is the text bar
+pub const SCREEN_PLOT_HEIGHT: usize = 224;
+pub const SCREEN_HORIZONTAL_DIVISIONS: usize = 10;
+pub const SCREEN_VERTICAL_DIVISIONS: usize = 8;
+
+/// Approximates the scope screen as a flat RGB buffer of `SCREEN_WIDTH` x `SCREEN_HEIGHT` pixels:
+/// the division grid, channel 1 in yellow, channel 2 in cyan and a text bar with the scales and vpp
+pub fn reconstruct_screen_image(data: &Data) -> Vec<u8> {
+    const GRID: [u8; 3] = [64, 64, 64];
+    const AXIS: [u8; 3] = [128, 128, 128];
+    const TEXT: [u8; 3] = [255, 255, 255];
+    const CHANNEL1: [u8; 3] = [255, 255, 0];
+    const CHANNEL2: [u8; 3] = [0, 255, 255];
+
+    let mut image = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 3];
+
+    let division_width = SCREEN_WIDTH / SCREEN_HORIZONTAL_DIVISIONS;
+    let division_height = SCREEN_PLOT_HEIGHT / SCREEN_VERTICAL_DIVISIONS;
+
+    for x in 0..SCREEN_WIDTH {
+        for y in 0..SCREEN_PLOT_HEIGHT {
+            if y == SCREEN_PLOT_HEIGHT / 2 || x == SCREEN_WIDTH / 2 {
+                set_pixel(&mut image, x, y, AXIS);
+            } else if x % division_width == 0 || y % division_height == 0 {
+                set_pixel(&mut image, x, y, GRID);
+            }
+        }
+    }
+
+    for (channel, color) in [(&data.channel1, CHANNEL1), (&data.channel2, CHANNEL2)] {
+        let volts_per_pixel = channel.effective_scale.get_scale() / division_height as f32;
+        let row = |voltage: f32| {
+            let y = SCREEN_PLOT_HEIGHT as f32 / 2.0 - voltage / volts_per_pixel;
+            y.clamp(0.0, (SCREEN_PLOT_HEIGHT - 1) as f32) as usize
+        };
+        let mut previous: Option<usize> = None;
+
+        for x in 0..SCREEN_WIDTH {
+            let index = x * channel.points.len() / SCREEN_WIDTH;
+            let y = match channel.points.get(index) {
+                Some(point) => row(point.voltage),
+                None => break
+            };
+            let (from, to) = match previous {
+                Some(previous) => (previous.min(y), previous.max(y)),
+                None => (y, y)
+            };
+
+            for y in from..=to {
+                set_pixel(&mut image, x, y, color);
+            }
+
+            previous = Some(y);
+        }
+    }
+
+    let text_y = SCREEN_PLOT_HEIGHT + 3;
+    let scales = format!("CH1 {} CH2 {} T {}", data.channel1.effective_scale, data.channel2.effective_scale, data.time_scale);
+    draw_text(&mut image, &scales, 2, text_y, TEXT);
+    draw_text(&mut image, &format!("Vpp {}", format_voltage_display(data.channel1.measurements.vpp)), 2, 2, CHANNEL1);
+    draw_text(&mut image, &format!("Vpp {}", format_voltage_display(data.channel2.measurements.vpp)), SCREEN_WIDTH / 2 + 2, 2, CHANNEL2);
+
+    image
+}
+
+fn set_pixel(image: &mut [u8], x: usize, y: usize, color: [u8; 3]) {
+    if x < SCREEN_WIDTH && y < SCREEN_HEIGHT {
+        let index = (y * SCREEN_WIDTH + x) * 3;
+        image[index..index + 3].copy_from_slice(&color);
+    }
+}
+
+/// Draws `text` with a 3x5 pixel font scaled by two, unknown characters are left blank
+fn draw_text(image: &mut [u8], text: &str, x: usize, y: usize, color: [u8; 3]) {
+    const PIXEL_SIZE: usize = 2;
+
+    for (position, character) in text.chars().enumerate() {
+        let glyph: [u8; 5] = match character {
+            '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+            '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+            '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+            '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+            '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+            '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+            '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+            '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+            '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+            '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+            '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+            '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+            'C' => [0b111, 0b100, 0b100, 0b100, 0b111],
+            'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+            'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+            'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+            'm' => [0b000, 0b000, 0b111, 0b111, 0b101],
+            'u' => [0b000, 0b000, 0b101, 0b101, 0b111],
+            'n' => [0b000, 0b000, 0b110, 0b101, 0b101],
+            's' => [0b000, 0b111, 0b100, 0b011, 0b111],
+            'p' => [0b000, 0b111, 0b101, 0b111, 0b100],
+            _ => [0; 5]
+        };
+        let left = x + position * 4 * PIXEL_SIZE;
+
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+
+                for dy in 0..PIXEL_SIZE {
+                    for dx in 0..PIXEL_SIZE {
+                        set_pixel(image, left + column * PIXEL_SIZE + dx, y + row * PIXEL_SIZE + dy, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "png")]
+pub fn write_png(data: &Data, path: &Path) -> Result<(), FnirsiError> {
+    let image = reconstruct_screen_image(data);
+    image::save_buffer(path, &image, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, image::ColorType::Rgb8)?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "png"))]
+pub fn write_png(_data: &Data, _path: &Path) -> Result<(), FnirsiError> {
+    Err(FnirsiError::FeatureDisabled("png"))
+}
+
+/// Writes the digitized channels as the logic probes `CH1` and `CH2` of a sigrok session file, the `.sr` zip
+/// PulseView opens. The channels that weren't digitized are left out, one byte holds a sample of every probe
+#[cfg(feature = "sigrok")]
+pub fn export_sigrok(data: &Data, path: &Path) -> Result<(), FnirsiError> {
+    let probes: Vec<(&str, &[bool])> = [("CH1", data.channel1_digital.as_deref()), ("CH2", data.channel2_digital.as_deref())]
+        .iter()
+        .filter_map(|(name, bits)| bits.map(|bits| (*name, bits)))
+        .collect();
+
+    if probes.is_empty() {
+        return Err(FnirsiError::NothingDigitized);
+    }
+
+    let samples = probes.iter().map(|(_, bits)| bits.len()).min().unwrap_or(0);
+    let logic: Vec<u8> = (0..samples)
+        .map(|index| probes.iter().enumerate().fold(0, |byte, (bit, (_, bits))| byte | (bits[index] as u8) << bit))
+        .collect();
+    let mut metadata = format!(
+        "[global]\nsigrok version=0.5.2\n\n[device 1]\ncapturefile=logic-1\ntotal probes={}\nsamplerate={} Hz\ntotal samples={}\nunitsize=1\n",
+        probes.len(),
+        sample_rate_hz(&data.time_scale).round() as u64,
+        samples
+    );
+
+    for (index, (name, _)) in probes.iter().enumerate() {
+        metadata.push_str(&format!("probe{}={}\n", index + 1, name));
+    }
+
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(path)?);
+    let options = zip::write::SimpleFileOptions::default();
+
+    for (name, contents) in [("version", &b"2"[..]), ("metadata", metadata.as_bytes()), ("logic-1-1", &logic)] {
+        writer.start_file(name, options)?;
+        writer.write_all(contents)?;
+    }
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "sigrok"))]
+pub fn export_sigrok(_data: &Data, _path: &Path) -> Result<(), FnirsiError> {
+    Err(FnirsiError::FeatureDisabled("sigrok"))
+}
+
+/// Writes the logic levels of channel 1 and 2 as a Value Change Dump for GTKWave and the other simulation viewers, in
+/// nanoseconds from the first sample. After the initial `$dumpvars` a timestamp is only written where a level changes,
+/// a channel with fewer samples keeps its last level
+pub fn export_vcd(data: &Data, digitized_ch1: &[bool], digitized_ch2: &[bool], sample_rate_hz: f64, path: &Path) -> Result<(), FnirsiError> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let level = |high: bool| if high { '1' } else { '0' };
+    let (mut ch1, mut ch2) = (digitized_ch1.first().copied().unwrap_or(false), digitized_ch2.first().copied().unwrap_or(false));
+
+    writeln!(writer, "$comment FNIRSI 1013D capture at {}/div $end", data.time_scale)?;
+    writeln!(writer, "$timescale 1ns $end")?;
+    writeln!(writer, "$scope module fnirsi $end")?;
+    writeln!(writer, "$var wire 1 ! CH1 $end")?;
+    writeln!(writer, "$var wire 1 \" CH2 $end")?;
+    writeln!(writer, "$upscope $end")?;
+    writeln!(writer, "$enddefinitions $end")?;
+    writeln!(writer, "#0")?;
+    writeln!(writer, "$dumpvars\n{}!\n{}\"\n$end", level(ch1), level(ch2))?;
+
+    for index in 1..digitized_ch1.len().max(digitized_ch2.len()) {
+        let next = (digitized_ch1.get(index).copied().unwrap_or(ch1), digitized_ch2.get(index).copied().unwrap_or(ch2));
+
+        if next == (ch1, ch2) {
+            continue;
+        }
+
+        writeln!(writer, "#{}", (index as f64 * 1e9 / sample_rate_hz).round() as u64)?;
+        if next.0 != ch1 {
+            writeln!(writer, "{}!", level(next.0))?;
+        }
+        if next.1 != ch2 {
+            writeln!(writer, "{}\"", level(next.1))?;
+        }
+        (ch1, ch2) = next;
+    }
+
+    Ok(writer.flush()?)
+}
+
+pub const CHART_WIDTH: u32 = 1024;
+pub const CHART_HEIGHT: u32 = 768;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartFormat {
+    Png,
+    Svg
+}
+
+/// Draws both channels over the 10x8 division grid of the scope, each trace in its own volts per division
+/// and the time axis from the first to the last point labeled at every division in the selected time unit
+#[cfg(feature = "plotters")]
+pub fn render_waveform_chart(data: &Data, path: &Path, format: ChartFormat) -> Result<(), FnirsiError> {
+    use plotters::prelude::{BitMapBackend, IntoDrawingArea, SVGBackend};
+
+    match format {
+        ChartFormat::Png => draw_waveform_chart(BitMapBackend::new(path, (CHART_WIDTH, CHART_HEIGHT)).into_drawing_area(), data)
+            .map_err(|error| FnirsiError::Chart(error.to_string())),
+        ChartFormat::Svg => draw_waveform_chart(SVGBackend::new(path, (CHART_WIDTH, CHART_HEIGHT)).into_drawing_area(), data)
+            .map_err(|error| FnirsiError::Chart(error.to_string()))
+    }
+}
+
+#[cfg(feature = "plotters")]
+fn draw_waveform_chart<DB: plotters::prelude::DrawingBackend>(root: plotters::prelude::DrawingArea<DB, plotters::coord::Shift>, data: &Data) -> Result<(), plotters::prelude::DrawingAreaErrorKind<DB::ErrorType>> {
+    use plotters::prelude::*;
+
+    let horizontal_divisions = SCREEN_HORIZONTAL_DIVISIONS as f32;
+    let vertical_divisions = SCREEN_VERTICAL_DIVISIONS as f32;
+    let start = data.channel1.points.first().map(|point| point.time).unwrap_or_default();
+    let end = data.channel1.points.last().map(|point| point.time).unwrap_or_default();
+    let time_per_division = if end > start { (end - start) / horizontal_divisions } else { 1.0 };
+    let division_of = |time: f32| (time - start) / time_per_division;
+    // The times of the points are in the selected unit, or in seconds without one
+    let time_label = |division: &f32| match data.time_unit {
+        Some(unit) => format!("{} {}", format_fixed(start + division * time_per_division, 3), unit.symbol()),
+        None => format_si(start + division * time_per_division, "s")
+    };
+    let time_desc = match data.time_unit {
+        Some(unit) => format!("Time ({})", unit.symbol()),
+        None => "Time".to_string()
+    };
+
+    root.fill(&BLACK)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .margin(20)
+        .x_label_area_size(50)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0f32..horizontal_divisions, -vertical_divisions / 2.0..vertical_divisions / 2.0)?;
+
+    chart.configure_mesh()
+        .x_labels(SCREEN_HORIZONTAL_DIVISIONS + 1)
+        .y_labels(SCREEN_VERTICAL_DIVISIONS + 1)
+        .disable_x_mesh()
+        .disable_y_mesh()
+        .x_label_formatter(&time_label)
+        .y_label_formatter(&|division| format!("{} div", division))
+        .x_desc(time_desc)
+        .y_desc("Divisions")
+        .axis_style(WHITE)
+        .label_style(("sans-serif", 14).into_font().color(&WHITE))
+        .draw()?;
+
+    let grid = RGBColor(64, 64, 64);
+
+    for division in 0..=SCREEN_HORIZONTAL_DIVISIONS {
+        chart.draw_series(LineSeries::new([(division as f32, -vertical_divisions / 2.0), (division as f32, vertical_divisions / 2.0)], grid))?;
+    }
+
+    for division in 0..=SCREEN_VERTICAL_DIVISIONS {
+        let y = division as f32 - vertical_divisions / 2.0;
+        chart.draw_series(LineSeries::new([(0.0, y), (horizontal_divisions, y)], grid))?;
+    }
+
+    for (name, channel, color) in [("CH1", &data.channel1, YELLOW), ("CH2", &data.channel2, CYAN)] {
+        let volts_per_division = channel.effective_scale.get_scale();
+        let label = format!(
+            "{} {}/div  Vpp {}  f {}",
+            name,
+            channel.effective_scale,
+            format_voltage_display(channel.measurements.vpp),
+            format_si(channel.measurements.frequency as f32, "Hz")
+        );
+
+        chart.draw_series(LineSeries::new(
+            channel.points.iter().map(|point| (division_of(point.time), point.voltage / volts_per_division)),
+            color
+        ))?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color));
+
+        let markers = annotate_measurements(&channel.points, &channel.measurements).into_iter()
+            .filter(|annotated| !annotated.annotations.is_empty())
+            .map(|annotated| {
+                let position = (division_of(annotated.point.time), annotated.point.voltage / volts_per_division);
+
+                EmptyElement::at(position)
+                    + Circle::new((0, 0), 4, color.filled())
+                    + Text::new(annotated.annotations.join(", "), (6, -14), ("sans-serif", 12).into_font().color(&color))
+            });
+        chart.draw_series(markers)?;
+    }
+
+    chart.configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(BLACK.mix(0.8))
+        .border_style(WHITE)
+        .label_font(("sans-serif", 14).into_font().color(&WHITE))
+        .draw()?;
+
+    root.present()
+}
+
+#[cfg(not(feature = "plotters"))]
+pub fn render_waveform_chart(_data: &Data, _path: &Path, _format: ChartFormat) -> Result<(), FnirsiError> {
+    Err(FnirsiError::FeatureDisabled("plotters"))
+}
+
+fn build_channel(context: &CaptureContext, channel: u8, time_scale: &Scale<Second>) -> Result<Channel, FnirsiError> {
+    let (header, options) = (&context.file.header, &context.options);
+    let (raw_scale, coupling, probe, offset, measurements) = match channel {
+        1 => (header.channel1_scale, header.channel1_coupling, header.channel1_probe, header.channel1_offset, &header.channel1_measurements),
+        _ => (header.channel2_scale, header.channel2_coupling, header.channel2_probe, header.channel2_offset, &header.channel2_measurements)
+    };
+    let index = channel as usize - 1;
+    let scale = match options.scale_override {
+        Some(scales) => scales[index],
+        None => {
+            let scale: Scale<Volt> = raw_scale.try_into()?;
+            log::debug!("channel{}_scale: {:x} = {} ({:b})", channel, raw_scale, scale, scale);
+            scale
+        }
+    };
+    let probe = ProbeConfig {
+        coupling: coupling.try_into()?,
+        scale,
+        attenuation: options.attenuation_override.map(|attenuations| attenuations[index]).unwrap_or_else(|| {
+            // Only reachable under `lenient`, `validate_header` rejects the unknown values otherwise
+            probe.try_into().unwrap_or_else(|_| {
+                log::warn!("Channel {} probe value {} is not a known attenuation, reading it as 1x", channel, probe);
+                Attenuation::OneX
+            })
+        })
+    };
+    let effective_scale = probe.effective_scale();
+    let calibration = options.calibration[index];
+    let mut samples = match options.roll_write_pointer {
+        Some(write_pointer) if header.scroll_speed == ScrollSpeed::Slow as u16 => linearize_roll_buffer(context.samples(channel), write_pointer),
+        Some(_) => {
+            log::debug!("Ignoring the roll mode write pointer of channel {}, the capture isn't in roll mode", channel);
+            context.samples(channel).to_vec()
+        },
+        None => context.samples(channel).to_vec()
+    };
+    // The times are generated from the index so they keep increasing from 0 when reversed
+    if options.reverse_samples {
+        samples.reverse();
+    }
+    let mut points = apply_calibration(&generate_points(&samples, &effective_scale, time_scale, offset), calibration.gain, calibration.offset_v);
+
+    if options.remove_dc && !points.is_empty() {
+        let average_v = points.iter().map(|point| point.voltage).sum::<f32>() / points.len() as f32;
+        points.iter_mut().for_each(|point| point.voltage -= average_v);
+    }
+
+    let mut measurements = process_measurements(measurements, options.frequency_word_order);
+
+    if options.human_measurements {
+        measurements.human = Some(HumanMeasurements {
+            vmax_display: format_voltage_display(measurements.vmax),
+            vmin_display: format_voltage_display(measurements.vmin),
+            vavg_display: format_voltage_display(measurements.vavg),
+            vrms_display: format_voltage_display(measurements.vrms),
+            vpp_display: format_voltage_display(measurements.vpp),
+            vp_display: format_voltage_display(measurements.vp)
+        });
+    }
+
+    if !is_plausible_frequency(measurements.frequency, time_scale) {
+        measurements.frequency_estimated_hz = estimate_frequency_autocorrelation(&points, sample_rate_hz(time_scale), options.min_autocorrelation);
+    }
+
+    if options.compare_frequencies {
+        measurements.frequency_comparison = Some(compare_frequencies(measurements.frequency, &points, sample_rate_hz(time_scale) as f64, options.tolerance));
+    }
+
+    if options.fft_period {
+        measurements.estimated_period_ns = period_estimation_fft(&points, sample_rate_hz(time_scale) as f64).map(|period_s| period_s * 1e9);
+    }
+
+    measurements.settling_time_s = settling_time(&points, options.settling_tolerance_percent);
+    measurements.crest_factor = crest_factor(&points);
+
+    let quality = channel_quality(&points, &effective_scale, &measurements, context.model.layout().vertical_divisions as f32);
+    let signal_class = classify_signal(&points);
+    let signal_type = options.classify.then(|| detect_signal_type(&points, sample_rate_hz(time_scale) as f64));
+    let spectrogram = options.spectrogram.as_ref()
+        .map(|spectrogram_options| spectrogram(&points, sample_rate_hz(time_scale) as f64, spectrogram_options.window_size, spectrogram_options.hop_size));
+
+    let extrapolated_points = options.extrapolation.as_ref()
+        .map(|extrapolation| extrapolate(&points, extrapolation.num_extra_samples, extrapolation.order));
+
+    let trigger_channel = match channel {
+        1 => TriggerChannel::Channel1,
+        _ => TriggerChannel::Channel2
+    };
+
+    Ok(Channel {
+        voltage_resolution_v: probe.voltage_resolution_v(),
+        offset_v: (offset as f32 - ADC_MIDPOINT as f32) * probe.voltage_resolution_v(),
+        is_trigger_source: TriggerChannel::try_from(header.trigger_channel).ok() == Some(trigger_channel),
+        probe,
+        effective_scale,
+        measurements,
+        quality,
+        signal_class,
+        signal_type,
+        spectrogram,
+        extrapolated_points,
+        display_points: None,
+        division_markers: None,
+        points
+    })
+}
+
+fn process_measurements(measurements: &Measurements, frequency_word_order: WordOrder) -> ProcessedMeasurements {
+    // NaN serializes as null should a voltage field ever be marked unknown
+    let voltage = |field_name, raw| decode_measurement(field_name, raw).unwrap_or(f32::NAN);
+
+    ProcessedMeasurements {
+        vmax: voltage("vmax", measurements.vmax),
+        vmin: voltage("vmin", measurements.vmin),
+        vavg: voltage("vavg", measurements.vavg),
+        vrms: voltage("vrms", measurements.vrms),
+        vpp: voltage("vpp", measurements.vpp),
+        vp: voltage("vp", measurements.vp),
+        frequency: parse_frequency_ordered(measurements.frequency_high, measurements.frequency_low, frequency_word_order),
+        frequency_estimated_hz: None,
+        estimated_period_ns: None,
+        frequency_comparison: None,
+        settling_time_s: None,
+        crest_factor: None,
+        cycle_ns: RawMeasurement { raw: measurements.cycle_ns },
+        time_plus_ns: RawMeasurement { raw: measurements.time_plus_ns },
+        time_minus_ns: RawMeasurement { raw: measurements.time_minus_ns },
+        duty_plus_percentage: measurements.duty_plus_percentage,
+        duty_minus_percentage: measurements.duty_minus_percentage,
+        human: None
+    }
+}
+
+/// Period of the signal measured by the scope in microseconds, see `decode_cycle_time`
+pub fn cycle_time_us(measurements: &ProcessedMeasurements) -> f64 {
+    decode_cycle_time(measurements.cycle_ns.raw) * 1e6
+}
+
+/// Width of the positive pulses measured by the scope in microseconds
+pub fn time_plus_us(measurements: &ProcessedMeasurements) -> f64 {
+    decode_cycle_time(measurements.time_plus_ns.raw) * 1e6
+}
+
+/// Width of the negative pulses measured by the scope in microseconds
+pub fn time_minus_us(measurements: &ProcessedMeasurements) -> f64 {
+    decode_cycle_time(measurements.time_minus_ns.raw) * 1e6
+}
+
+/// Formats `value` with the SI prefix that keeps the number between 1 and 1000, e.g. `250 mV`
+pub fn format_si(value: f32, unit: &str) -> String {
+    let (scaled, prefix) = match value.abs() {
+        magnitude if magnitude == 0.0 || magnitude >= 1.0 => (value, ""),
+        magnitude if magnitude >= 1e-3 => (value * 1e3, "m"),
+        magnitude if magnitude >= 1e-6 => (value * 1e6, "u"),
+        _ => (value * 1e9, "n")
+    };
+    format!("{} {}{}", format_fixed(scaled, 3), prefix, unit)
+}
+
+/// Formats `value` with at most `decimals` decimals and without trailing zeros.
+/// All human readable numbers go through here, the std formatting it uses never looks at the locale
+/// so the decimal separator is always `.` whatever `LANG` or `LC_NUMERIC` are set to.
+pub fn format_fixed(value: f32, decimals: usize) -> String {
+    let number = format!("{:.*}", decimals, value);
+
+    if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        number
+    }
+}
+
+pub fn format_voltage_display(voltage: f32) -> String {
+    format_si(voltage, "V")
+}
+
+/// One line summary of a channel like `10mV/div DC 1× Trig  Vpp=6.59 V  Vmax=3.3 V  Vmin=-3.29 V  Freq=1000 Hz  SNR=45 dB  Pts=1500`
+pub fn channel_metrics_summary(ch: &Channel) -> String {
+    channel_metrics_summary_width(ch, usize::MAX)
+}
+
+/// `channel_metrics_summary` without the trailing fields that don't fit in `width` characters, the probe and the trigger source are always kept
+pub fn channel_metrics_summary_width(ch: &Channel, width: usize) -> String {
+    let measurements = &ch.measurements;
+    let frequency_hz = measurements.frequency_estimated_hz.unwrap_or(measurements.frequency as f32);
+    let rms_v = if ch.points.is_empty() { 0.0 } else {
+        (ch.points.iter().map(|point| point.voltage.powi(2)).sum::<f32>() / ch.points.len() as f32).sqrt()
+    };
+
+    let mut fields = vec![
+        format!("{}/div {} {}{}", ch.effective_scale, ch.probe.coupling, ch.probe.attenuation, if ch.is_trigger_source { " Trig" } else { "" }),
+        format!("Vpp={}", format_voltage_display(measurements.vpp)),
+        format!("Vmax={}", format_voltage_display(measurements.vmax)),
+        format!("Vmin={}", format_voltage_display(measurements.vmin)),
+        format!("Freq={}", format_si(frequency_hz, "Hz"))
+    ];
+
+    if ch.quality.noise_v > 0.0 && rms_v > 0.0 {
+        fields.push(format!("SNR={} dB", format_fixed(20.0 * (rms_v / ch.quality.noise_v).log10(), 0)));
+    }
+    fields.push(format!("Pts={}", ch.points.len()));
+
+    let mut summary = fields[0].clone();
+
+    for field in &fields[1..] {
+        if summary.chars().count() + 2 + field.chars().count() > width {
+            break;
+        }
+        summary.push_str("  ");
+        summary.push_str(field);
+    }
+
+    summary
+}
+
+/// Terminal preview of both channels, each with a line of its scales cut to `width` above a `width` by `height` character plot.
+/// Every column covers the voltages of the points falling in it, the screen spans the `vertical_divisions` around 0V
+pub fn render_ascii(data: &Data, width: usize, height: usize, vertical_divisions: f32) -> String {
+    let (width, height) = (width.max(1), height.max(1));
+    let mut output = String::new();
+
+    for (name, channel) in [("CH1", &data.channel1), ("CH2", &data.channel2)] {
+        let scales = format!("{} {}/div  T {}/div", name, channel.effective_scale, data.time_scale);
+        output.extend(scales.chars().take(width));
+        output.push('\n');
+
+        let half_screen_v = channel.effective_scale.get_scale() * vertical_divisions / 2.0;
+        let row = |voltage: f32| (((half_screen_v - voltage) / (2.0 * half_screen_v) * height as f32) as isize).clamp(0, height as isize - 1) as usize;
+        let mut columns: Vec<Option<(usize, usize)>> = vec![None; width];
+
+        for (index, point) in channel.points.iter().enumerate() {
+            let column = index * width / channel.points.len();
+            let point_row = row(point.voltage);
+            columns[column] = Some(match columns[column] {
+                Some((top, bottom)) => (top.min(point_row), bottom.max(point_row)),
+                None => (point_row, point_row)
+            });
+        }
+
+        let zero_row = row(0.0);
+
+        for line in 0..height {
+            let plot: String = columns.iter().map(|column| match column {
+                Some((top, bottom)) if (*top..=*bottom).contains(&line) => '█',
+                _ if line == zero_row => '·',
+                _ => ' '
+            }).collect();
+            output.push_str(plot.trim_end());
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+#[derive(Debug, Serialize)]
+pub struct Data {
+    /// Position of the capture in a sequence of captures
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence_index: Option<u32>,
+    pub trigger: Trigger,
+    pub time_scale: Scale<Second>,
+    /// Unit of the times of the points, the other times stay in seconds. Absent for seconds by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_unit: Option<TimeUnit>,
+    /// Score of the worst channel between 0 and 1
+    pub quality_score: f32,
+    pub channel1: Channel,
+    pub channel2: Channel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel1_pulse_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase_deg: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel1_sine_fit: Option<SineFit>,
+    /// Magnitude squared coherence between the channels as `(frequency, coherence)` pairs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coherence: Option<Vec<(f64, f64)>>,
+    /// Channel 2 relative to channel 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transfer_function: Option<TransferFunction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel1_histogram: Option<Vec<HistogramBin>>,
+    /// Distribution of the durations in seconds of the channel 1 pulses above the threshold, the bounds are in seconds despite their name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel1_high_pulse_histogram: Option<Vec<HistogramBin>>,
+    /// Distribution of the durations in seconds of the channel 1 pulses below the threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel1_low_pulse_histogram: Option<Vec<HistogramBin>>,
+    /// Measurements the device doesn't know are NaN and serialize as null in json
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel1_pulse_train: Option<PulseTrainMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel1_glitches: Option<Vec<GlitchEvent>>,
+    /// Both channels merged with the `ChannelMerge` of the options, before normalization
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merged_points: Option<Vec<Point>>,
+    /// Channel 1 minus channel 2, see `differential_channel`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub differential: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel1_digital: Option<Vec<bool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel2_digital: Option<Vec<bool>>,
+    /// Words of the parallel bus, `ceil(bits / 8)` bytes per sample with the first bit in the lowest bit of the first byte
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_words: Option<Vec<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<Diagnostics>,
+    /// Scale at the probe tip like `500mV/div` channel 1 would read best at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_ch1_scale: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_ch2_scale: Option<String>,
+    /// Time scale the frequency of the trigger source would read best at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_time_scale: Option<String>,
+    /// What looked wrong in the capture as `code: message`, the codes are `suspicious_header`, `placeholder_scale`,
+    /// `unknown_attenuation`, `implausible_frequency`, `vpp_mismatch`, `period_mismatch`, `duty_mismatch`,
+    /// `samples_out_of_range` and `clipping`
+    pub warnings: Vec<String>
+}
+
+/// What the parse read and which fallbacks it took, for figuring out why an output looks wrong
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    pub channel1_samples: usize,
+    pub channel2_samples: usize,
+    pub channel1_zoom_samples: usize,
+    pub channel2_zoom_samples: usize,
+    pub bytes_read: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<usize>,
+    /// Every place the parse fell back to a guess, an estimate or an override
+    pub fallbacks: Vec<String>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PulseTrainMetrics {
+    /// Rising edges through the threshold
+    pub num_pulses: u32,
+    /// Between consecutive rising edges
+    pub mean_period_ns: f64,
+    pub period_stddev_ns: f64,
+    pub mean_high_time_ns: f64,
+    pub mean_low_time_ns: f64,
+    /// Fraction of the period spent above the threshold
+    pub duty_cycle: f32,
+    pub frequency_hz: f64,
+    pub rise_time_ns: f64,
+    pub fall_time_ns: f64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct GlitchEvent {
+    /// First sample across the threshold
+    pub start_sample: usize,
+    /// Last sample across the threshold
+    pub end_sample: usize,
+    /// Voltage furthest across the threshold
+    pub peak_voltage_v: f32
+}
+
+/// `amplitude_v * sin(2π * frequency_hz * t + phase_rad) + dc_offset_v`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SineFit {
+    pub amplitude_v: f64,
+    pub frequency_hz: f64,
+    /// Phase at time 0 in `(-π, π]`
+    pub phase_rad: f64,
+    pub dc_offset_v: f64,
+    /// Fraction of the variance of the points explained by the sine, 1 for a perfect sine
+    pub r_squared: f64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct HistogramBin {
+    pub min_v: f32,
+    pub max_v: f32,
+    pub count: usize
+}
+
+#[derive(Debug, Serialize)]
+pub struct Channel {
+    #[serde(flatten)]
+    pub probe: ProbeConfig,
+    /// Volts per division at the probe tip
+    pub effective_scale: Scale<Volt>,
+    pub voltage_resolution_v: f32,
+    /// Vertical position of the trace, the header offset away from the center of the screen in volts at the probe tip
+    pub offset_v: f32,
+    /// Whether the trigger of the capture watches this channel
+    pub is_trigger_source: bool,
+    pub measurements: ProcessedMeasurements,
+    pub quality: ChannelQuality,
+    /// Classification from the points, independent of the coupling in the header
+    pub signal_class: SignalClass,
+    /// Waveform recognized from the points, see `detect_signal_type`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal_type: Option<SignalType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spectrogram: Option<Spectrogram>,
+    /// The points followed by their extrapolation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extrapolated_points: Option<Vec<SamplePoint>>,
+    /// The points reduced to the width of a display, see `downsample_minmax`. The times are in seconds whatever the time unit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_points: Option<Vec<MinMaxPoint>>,
+    /// Indices of the points on the vertical grid lines of the screen, see `division_markers`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub division_markers: Option<Vec<usize>>,
+    pub points: Vec<Point>
+}
+
+/// The voltage range of consecutive points drawn in the same column of a display
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MinMaxPoint {
+    /// Time of the first of the points
+    pub time_s: f64,
+    pub min_v: f32,
+    pub max_v: f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalClass {
+    /// The average makes up nearly all of the RMS
+    Dc,
+    /// The signal swings around 0 V
+    Ac,
+    /// An AC signal riding on a DC level
+    Mixed
+}
+
+/// Shape of a periodic waveform as recognized by `detect_signal_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalType {
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+    /// Without a fundamental the spectrum is made of
+    Noise,
+    Dc,
+    /// Periodic but none of the other shapes
+    Unknown
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransferFunction {
+    pub frequency_bins: Vec<f64>,
+    /// Gain of the output over the input
+    pub magnitude_db: Vec<f64>,
+    /// Phase of the output relative to the input, between -180 and 180
+    pub phase_deg: Vec<f64>
+}
+
+/// Gain and phase of the output channel relative to the input channel at the frequency of one capture of a sweep
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BodePoint {
+    pub frequency_hz: f64,
+    pub gain_db: f64,
+    /// Between -180 and 180
+    pub phase_deg: f64
+}
+
+/// The points of a Bode plot measured one capture per frequency, from the lowest frequency up
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrequencySweepResult {
+    pub points: Vec<BodePoint>
+}
+
+#[derive(Debug, Serialize)]
+pub struct Spectrogram {
+    /// Time of the center of every window
+    pub time_bins: Vec<f64>,
+    pub frequency_bins: Vec<f64>,
+    /// One row of magnitudes per time bin with one column per frequency bin
+    pub magnitude_db: Vec<Vec<f64>>
+}
+
+/// The factors the quality score of a channel is the product of
+#[derive(Debug, Serialize)]
+pub struct ChannelQuality {
+    pub score: f32,
+    /// Fraction of the points at or beyond the top or bottom of the screen
+    pub clipping_fraction: f32,
+    /// Standard deviation of the sample to sample noise estimated from the second differences
+    pub noise_v: f32,
+    /// Relative difference between the device vpp and the vpp of the points
+    pub vpp_mismatch: f32
+}
+
+/// The coupling, volts per division and probe attenuation the scope sets together for a channel
+#[derive(Debug, Serialize)]
+pub struct ProbeConfig {
+    pub scale: Scale<Volt>,
+    pub coupling: Coupling,
+    pub attenuation: Attenuation
+}
+
+impl ProbeConfig {
+    /// Volts per division at the probe tip, the scale of the header is the one at the scope input
+    pub fn effective_scale(&self) -> Scale<Volt> {
+        self.scale.with_attenuation(self.attenuation)
+    }
+
+    /// Voltage from the bottom to the top of a screen grid of `vertical_divisions`
+    pub fn full_scale_range_v(&self, vertical_divisions: f32) -> f32 {
+        self.effective_scale().get_scale() * vertical_divisions
+    }
+
+    /// Volts per ADC count, the smallest voltage step the capture can represent
+    pub fn voltage_resolution_v(&self) -> f32 {
+        self.effective_scale().get_scale() / DIVISION_POINTS
+    }
+}
+
+impl Display for ProbeConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}/div {}", self.coupling, self.scale, self.attenuation)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Trigger {
+    pub trigger_type: TriggerType,
+    pub edge: TriggerEdge,
+    pub channel: TriggerChannel,
+    pub trigger_50: Trigger50,
+    /// Only known when the 50% trigger is on, the scope then triggers at the midpoint of the trigger channel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level_v: Option<f32>
+}
+
+/// Whether the device `vpp` agrees with its `vmax - vmin` within `tolerance` of the larger of them. Unknown values can't
+/// disagree, and neither can the `vmin` of 0 V that the unsigned field holds for a signal swinging below 0 V
+pub fn vpp_consistent(measurements: &ProcessedMeasurements, tolerance: f32) -> bool {
+    let span = measurements.vmax - measurements.vmin;
+    let negative_minimum = measurements.vmin == 0.0 && measurements.vpp > measurements.vmax;
+
+    negative_minimum || span.is_nan() || measurements.vpp.is_nan()
+        || (measurements.vpp - span).abs() <= tolerance * measurements.vpp.abs().max(span.abs())
+}
+
+/// Whether the device `cycle_ns` agrees with the period of its `frequency` within `tolerance` of the longer of them.
+/// Captures without either can't disagree, and neither can frequencies slower than the longest cycle time the field holds
+pub fn period_consistent(measurements: &ProcessedMeasurements, tolerance: f32) -> bool {
+    if measurements.cycle_ns.raw == 0 || measurements.frequency == 0 || (measurements.frequency as f64) < 1.0 / decode_cycle_time(u16::MAX) {
+        return true;
+    }
+
+    let (cycle_s, period_s) = (decode_cycle_time(measurements.cycle_ns.raw), 1.0 / measurements.frequency as f64);
+    (cycle_s - period_s).abs() <= tolerance as f64 * cycle_s.max(period_s)
+}
+
+/// Whether the positive and negative duty cycles of the device add up to 100% within `tolerance` of it, the 0% of both
+/// the device reports for signals without edges can't disagree
+pub fn duty_consistent(measurements: &ProcessedMeasurements, tolerance: f32) -> bool {
+    let sum = measurements.duty_plus_percentage as f32 + measurements.duty_minus_percentage as f32;
+
+    sum == 0.0 || (sum - 100.0).abs() <= tolerance * 100.0
+}
+
+/// A device frequency is only trusted when it is non zero and below the nyquist limit of the capture
+pub fn is_plausible_frequency(frequency: u32, time_scale: &Scale<Second>) -> bool {
+    frequency != 0 && (frequency as f32) < sample_rate_hz(time_scale) / 2.0
+}
+
+/// Estimates the dominant frequency from the first autocorrelation peak after the first zero crossing.
+/// Returns `None` when no peak reaches `min_correlation`.
+pub fn estimate_frequency_autocorrelation(points: &[Point], sample_rate_hz: f32, min_correlation: f32) -> Option<f32> {
+    let mean = points.iter().map(|point| point.voltage).sum::<f32>() / points.len() as f32;
+    let values: Vec<f32> = points.iter().map(|point| point.voltage - mean).collect();
+    let energy: f32 = values.iter().map(|value| value * value).sum();
+
+    if energy == 0.0 {
+        return None;
+    }
+
+    let correlations: Vec<f32> = (0..values.len() / 2).map(|lag| {
+        let sum: f32 = values.iter().zip(&values[lag..]).map(|(a, b)| a * b).sum();
+        sum * values.len() as f32 / ((values.len() - lag) as f32 * energy)
+    }).collect();
+
+    let first_negative = correlations.iter().position(|correlation| *correlation < 0.0)?;
+    let peak_lag = (first_negative..correlations.len() - 1).find(|lag| {
+        let correlation = correlations[*lag];
+        correlation >= min_correlation && correlation >= correlations[lag - 1] && correlation >= correlations[lag + 1]
+    })?;
+
+    let (before, peak, after) = (correlations[peak_lag - 1], correlations[peak_lag], correlations[peak_lag + 1]);
+    let curvature = before - 2.0 * peak + after;
+    let refinement = if curvature == 0.0 { 0.0 } else { 0.5 * (before - after) / curvature };
+
+    Some(sample_rate_hz / (peak_lag as f32 + refinement))
+}
+
+/// Counts the rising (or falling when `active_high` is false) edges through `threshold_v`.
+/// The signal has to leave the `hysteresis_v` wide band around the threshold before it changes state.
+pub fn count_pulses(points: &[Point], threshold_v: f32, hysteresis_v: f32, active_high: bool) -> u32 {
+    let (low, high) = (threshold_v - hysteresis_v / 2.0, threshold_v + hysteresis_v / 2.0);
+    let mut state = match points.first() {
+        Some(point) => point.voltage > threshold_v,
+        None => return 0
+    };
+    let mut pulses = 0;
+
+    for point in points {
+        if !state && point.voltage > high {
+            state = true;
+            pulses += active_high as u32;
+        } else if state && point.voltage < low {
+            state = false;
+            pulses += !active_high as u32;
+        }
+    }
+
+    pulses
+}
+
+/// Distribution of the voltages in `num_bins` bins of equal width between the lowest and the highest voltage, empty bins included.
+/// Every point lands in the first bin when all voltages are equal.
+pub fn voltage_histogram(points: &[Point], num_bins: usize) -> Vec<HistogramBin> {
+    histogram(&points.iter().map(|point| point.voltage).collect::<Vec<f32>>(), num_bins)
+}
+
+/// Time from the start of a step, when the signal leaves the band around its initial value, until it last leaves the
+/// band of `tolerance_percent` of the step around its final value. The initial and final values are the averages of the
+/// first and last tenth of the capture. Returns `None` unless the step spans at least half the signal and the signal
+/// ends settled.
+pub fn settling_time(points: &[Point], tolerance_percent: f32) -> Option<f32> {
+    let edge_samples = points.len() / 10;
+
+    if edge_samples == 0 {
+        return None;
+    }
+
+    let average = |points: &[Point]| points.iter().map(|point| point.voltage).sum::<f32>() / points.len() as f32;
+    let (initial_v, final_v) = (average(&points[..edge_samples]), average(&points[points.len() - edge_samples..]));
+    let step_v = (final_v - initial_v).abs();
+    let min = points.iter().map(|point| point.voltage).fold(f32::INFINITY, f32::min);
+    let max = points.iter().map(|point| point.voltage).fold(f32::NEG_INFINITY, f32::max);
+    let tolerance_v = step_v * tolerance_percent / 100.0;
+
+    if step_v == 0.0 || step_v < (max - min) / 2.0 {
+        return None;
+    }
+
+    let start = points.iter().find(|point| (point.voltage - initial_v).abs() > tolerance_v)?;
+    let last_outside = points.iter().rposition(|point| (point.voltage - final_v).abs() > tolerance_v)?;
+
+    if last_outside >= points.len() - edge_samples {
+        return None;
+    }
+
+    Some(points[last_outside + 1].time - start.time)
+}
+
+/// The points followed by `num_extra_samples` predictions of an AR(`order`) model fitted with the Yule-Walker equations
+/// to the last `min(4 * order, points.len())` samples, continuing the spacing of the first two points
+pub fn extrapolate(points: &[Point], num_extra_samples: usize, order: usize) -> Vec<SamplePoint> {
+    let mut extrapolated: Vec<SamplePoint> = points.iter()
+        .map(|point| SamplePoint { time: point.time, voltage: point.voltage, extrapolated: false })
+        .collect();
+    let last = match points.last() {
+        Some(last) => *last,
+        None => return extrapolated
+    };
+    let step = if points.len() > 1 { points[1].time - points[0].time } else { 0.0 };
+
+    let fitted: Vec<f64> = points[points.len() - (4 * order).min(points.len())..].iter().map(|point| point.voltage as f64).collect();
+    let mean = if fitted.is_empty() { last.voltage as f64 } else { fitted.iter().sum::<f64>() / fitted.len() as f64 };
+    let order = order.min(fitted.len().saturating_sub(1));
+    let autocorrelation: Vec<f64> = (0..=order)
+        .map(|lag| fitted.iter().zip(&fitted[lag..]).map(|(a, b)| (a - mean) * (b - mean)).sum::<f64>() / fitted.len() as f64)
+        .collect();
+    let coefficients = levinson_durbin(&autocorrelation);
+
+    // Deviations from the mean of the most recent samples, the newest last
+    let mut history: Vec<f64> = points[points.len() - order..].iter().map(|point| point.voltage as f64 - mean).collect();
+
+    for sample in 1..=num_extra_samples {
+        let prediction: f64 = coefficients.iter().zip(history.iter().rev()).map(|(coefficient, value)| coefficient * value).sum();
+
+        if order > 0 {
+            history.remove(0);
+            history.push(prediction);
+        }
+
+        extrapolated.push(SamplePoint { time: last.time + step * sample as f32, voltage: (mean + prediction) as f32, extrapolated: true });
+    }
+
+    extrapolated
+}
+
+/// Solves the Yule-Walker equations for the AR coefficients of order `autocorrelation.len() - 1`, lag 1 first
+fn levinson_durbin(autocorrelation: &[f64]) -> Vec<f64> {
+    let order = autocorrelation.len().saturating_sub(1);
+    let mut coefficients = vec![0.0; order];
+    let mut error = match autocorrelation.first() {
+        Some(&power) if power > 0.0 => power,
+        _ => return coefficients
+    };
+
+    for step in 0..order {
+        let reflection = (autocorrelation[step + 1] - (0..step).map(|index| coefficients[index] * autocorrelation[step - index]).sum::<f64>()) / error;
+        let previous = coefficients.clone();
+
+        coefficients[step] = reflection;
+        for index in 0..step {
+            coefficients[index] = previous[index] - reflection * previous[step - 1 - index];
+        }
+        error *= 1.0 - reflection * reflection;
+
+        if error <= 0.0 {
+            break;
+        }
+    }
+
+    coefficients
+}
+
+/// Finds the excursions across `threshold_v`, upwards or downwards, that return within `max_width_samples` samples.
+/// Excursions still going on at the start or the end of the capture aren't glitches as their width is unknown.
+pub fn find_glitches(points: &[Point], max_width_samples: usize, threshold_v: f32) -> Vec<GlitchEvent> {
+    let mut glitches = Vec::new();
+    let mut start = 0;
+
+    // Every run of samples on the same side of the threshold bounded by samples on the other side on both ends
+    while start < points.len() {
+        let above = points[start].voltage > threshold_v;
+        let end = points[start..].iter().position(|point| (point.voltage > threshold_v) != above).map_or(points.len(), |length| start + length);
+
+        if start > 0 && end < points.len() && end - start <= max_width_samples {
+            let voltages = points[start..end].iter().map(|point| point.voltage);
+
+            glitches.push(GlitchEvent {
+                start_sample: start,
+                end_sample: end - 1,
+                peak_voltage_v: if above { voltages.fold(f32::NEG_INFINITY, f32::max) } else { voltages.fold(f32::INFINITY, f32::min) }
+            });
+        }
+
+        start = end;
+    }
+
+    glitches
+}
+
+/// Distributions of the durations of the pulses above `threshold_v` and of the pulses below it, as `(high, low)`.
+/// Only pulses with both edges inside the capture are counted, the bounds of the bins are in seconds.
+/// Every pulse lands in the first bin when all pulses are as wide.
+pub fn time_histogram(points: &[Point], threshold_v: f32, num_bins: usize) -> (Vec<HistogramBin>, Vec<HistogramBin>) {
+    let (mut high_pulses, mut low_pulses) = (Vec::new(), Vec::new());
+
+    for pair in threshold_crossings(points, threshold_v).windows(2) {
+        match (pair[0], pair[1]) {
+            ((start, true), (end, false)) => high_pulses.push(end - start),
+            ((start, false), (end, true)) => low_pulses.push(end - start),
+            _ => {}
+        }
+    }
+
+    (histogram(&high_pulses, num_bins), histogram(&low_pulses, num_bins))
+}
+
+/// Linearly interpolated times at which the signal crosses `threshold_v`, paired with whether it crosses upwards
+fn threshold_crossings(points: &[Point], threshold_v: f32) -> Vec<(f32, bool)> {
+    points.windows(2).filter_map(|pair| {
+        let (before, after) = (pair[0], pair[1]);
+        let rising = before.voltage < threshold_v && after.voltage >= threshold_v;
+        let falling = before.voltage >= threshold_v && after.voltage < threshold_v;
+
+        if !rising && !falling {
+            return None;
+        }
+
+        let fraction = (threshold_v - before.voltage) / (after.voltage - before.voltage);
+        Some((before.time + fraction * (after.time - before.time), rising))
+    }).collect()
+}
+
+/// Period, pulse widths and edge times of the pulses crossing `threshold_v`, from interpolated edge times.
+/// The rise and fall times are between 10% and 90% of the span between the lowest and highest voltage.
+/// Metrics that need more edges than the capture has are NaN.
+pub fn pulse_train_analysis(points: &[Point], threshold_v: f32) -> PulseTrainMetrics {
+    let crossings = threshold_crossings(points, threshold_v);
+    let rising: Vec<f64> = crossings.iter().filter(|(_, rising)| *rising).map(|(time, _)| *time as f64 * 1e9).collect();
+    let periods: Vec<f64> = rising.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let (mut high_times, mut low_times) = (Vec::new(), Vec::new());
+
+    for pair in crossings.windows(2) {
+        let width = (pair[1].0 - pair[0].0) as f64 * 1e9;
+        if pair[0].1 { high_times.push(width) } else { low_times.push(width) }
+    }
+
+    let mean = |values: &[f64]| if values.is_empty() { f64::NAN } else { values.iter().sum::<f64>() / values.len() as f64 };
+    let mean_period_ns = mean(&periods);
+    let (mean_high_time_ns, mean_low_time_ns) = (mean(&high_times), mean(&low_times));
+
+    let min = points.iter().map(|point| point.voltage).fold(f32::INFINITY, f32::min);
+    let max = points.iter().map(|point| point.voltage).fold(f32::NEG_INFINITY, f32::max);
+    let (low_crossings, high_crossings) = (threshold_crossings(points, min + 0.1 * (max - min)), threshold_crossings(points, min + 0.9 * (max - min)));
+    // Every crossing of the far level paired with the last crossing of the near level before it in the same direction
+    let edge_times = |from: &[(f32, bool)], to: &[(f32, bool)], direction: bool| -> Vec<f64> {
+        to.iter().filter(|(_, rising)| *rising == direction).filter_map(|(end, _)| {
+            from.iter().rev().find(|(start, rising)| *rising == direction && start <= end).map(|(start, _)| (end - start) as f64 * 1e9)
+        }).collect()
+    };
+
+    PulseTrainMetrics {
+        num_pulses: rising.len() as u32,
+        mean_period_ns,
+        period_stddev_ns: (mean(&periods.iter().map(|period| (period - mean_period_ns).powi(2)).collect::<Vec<f64>>())).sqrt(),
+        mean_high_time_ns,
+        mean_low_time_ns,
+        duty_cycle: (mean_high_time_ns / (mean_high_time_ns + mean_low_time_ns)) as f32,
+        frequency_hz: 1e9 / mean_period_ns,
+        rise_time_ns: mean(&edge_times(&low_crossings, &high_crossings, true)),
+        fall_time_ns: mean(&edge_times(&high_crossings, &low_crossings, false))
+    }
+}
+
+fn histogram(values: &[f32], num_bins: usize) -> Vec<HistogramBin> {
+    if num_bins == 0 || values.is_empty() {
+        return Vec::new();
+    }
+
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let width = if max - min > HISTOGRAM_RESOLUTION * min.abs().max(max.abs()) { (max - min) / num_bins as f32 } else { 0.0 };
+    let mut bins: Vec<HistogramBin> = (0..num_bins)
+        .map(|bin| HistogramBin { min_v: min + width * bin as f32, max_v: min + width * (bin + 1) as f32, count: 0 })
+        .collect();
+
+    for value in values {
+        // The highest value belongs to the last bin rather than to one past it
+        let bin = if width > 0.0 { (((value - min) / width) as usize).min(num_bins - 1) } else { 0 };
+        bins[bin].count += 1;
+    }
+
+    bins
+}
+
+/// The scales a capture of these measurements reads best at, the `PROBE_SCALES` entry the `vpp` spans 4 to 6 of the
+/// 8 divisions at and the `TIME_SCALES` entry showing 2 to 5 cycles of the frequency on the screen, or the nearest ones.
+/// The voltage scale is the one for a 1x probe, a flat signal gets the finest and one without frequency the slowest time scale
+pub fn auto_scale_suggestion(measurements: &ProcessedMeasurements) -> (Scale<Volt>, Scale<Second>) {
+    let frequency_hz = measurements.frequency_estimated_hz.unwrap_or(measurements.frequency as f32);
+
+    (suggest_voltage_scale(measurements.vpp), suggest_time_scale(frequency_hz))
+}
+
+fn suggest_voltage_scale(vpp_v: f32) -> Scale<Volt> {
+    if vpp_v.is_nan() || vpp_v <= 0.0 {
+        return PROBE_SCALES.iter().rev().find(|entry| entry.is_valid).unwrap().scale;
+    }
+
+    nearest_scale(&PROBE_SCALES, |volts_per_division| vpp_v as f64 / volts_per_division / SUGGESTED_VPP_DIVISIONS)
+}
+
+fn suggest_time_scale(frequency_hz: f32) -> Scale<Second> {
+    if frequency_hz.is_nan() || frequency_hz <= 0.0 {
+        return TIME_SCALES.iter().find(|entry| entry.is_valid).unwrap().scale;
+    }
+
+    nearest_scale(&TIME_SCALES, |seconds_per_division| {
+        frequency_hz as f64 * seconds_per_division * SCREEN_HORIZONTAL_DIVISIONS as f64 / SUGGESTED_CYCLES
+    })
+}
+
+/// The valid entry whose `ratio` to the ideal is the closest to 1 by factor. The 1-2-5 steps on either side of a
+/// geometric middle are as close, those ties within rounding go to the first of them
+fn nearest_scale<T: Unit>(scales: &[ScaleEntry<T>], ratio: impl Fn(f64) -> f64) -> Scale<T> {
+    let distance = |scale: &Scale<T>| ratio(scale.value as f64 * 10f64.powi(scale.scale)).ln().abs();
+
+    scales.iter()
+        .filter(|entry| entry.is_valid)
+        .map(|entry| entry.scale)
+        .fold(None, |nearest: Option<Scale<T>>, scale| match nearest {
+            Some(nearest) if distance(&nearest) <= distance(&scale) + 1e-9 => Some(nearest),
+            _ => Some(scale)
+        })
+        .unwrap()
+}
+
+/// Scores how usable a channel is, `vertical_divisions` of the screen grid bound the voltages that aren't clipped
+pub fn channel_quality(points: &[Point], scale: &Scale<Volt>, measurements: &ProcessedMeasurements, vertical_divisions: f32) -> ChannelQuality {
+    let clip_voltage = scale.get_scale() * vertical_divisions / 2.0;
+    let clipped = points.iter().filter(|point| point.voltage.abs() >= clip_voltage).count();
+    let clipping_fraction = if points.is_empty() { 0.0 } else { clipped as f32 / points.len() as f32 };
+
+    // The second difference of neighbouring samples cancels the slope of the signal and leaves the noise,
+    // whose variance it multiplies by 6
+    let differences: Vec<f32> = points.windows(3).map(|triple| triple[0].voltage - 2.0 * triple[1].voltage + triple[2].voltage).collect();
+    let noise_v = standard_deviation(&differences) / 6f32.sqrt();
+
+    let min = points.iter().map(|point| point.voltage).fold(f32::INFINITY, f32::min);
+    let max = points.iter().map(|point| point.voltage).fold(f32::NEG_INFINITY, f32::max);
+    let span = if points.is_empty() { 0.0 } else { max - min };
+    let noise_score = if span > 0.0 { 1.0 - (noise_v / span / MAX_NOISE_RATIO).min(1.0) } else { 1.0 };
+
+    let largest_vpp = span.max(measurements.vpp);
+    let vpp_mismatch = if largest_vpp > 0.0 { (span - measurements.vpp).abs() / largest_vpp } else { 0.0 };
+
+    ChannelQuality {
+        score: (1.0 - clipping_fraction) * noise_score * (1.0 - vpp_mismatch),
+        clipping_fraction,
+        noise_v,
+        vpp_mismatch
+    }
+}
+
+/// Classifies the points by the ratio of their absolute average to their RMS, a flat line at 0 V counts as DC
+pub fn classify_signal(points: &[Point]) -> SignalClass {
+    if points.is_empty() {
+        return SignalClass::Dc;
+    }
+
+    let mean_v = points.iter().map(|point| point.voltage as f64).sum::<f64>() / points.len() as f64;
+    let rms_v = (points.iter().map(|point| (point.voltage as f64).powi(2)).sum::<f64>() / points.len() as f64).sqrt();
+
+    if rms_v == 0.0 {
+        return SignalClass::Dc;
+    }
+
+    match (mean_v.abs() / rms_v) as f32 {
+        ratio if ratio >= DC_MEAN_TO_RMS => SignalClass::Dc,
+        ratio if ratio <= AC_MEAN_TO_RMS => SignalClass::Ac,
+        _ => SignalClass::Mixed
+    }
+}
+
+/// A point with the measurements it stands for, like `Vmax` where the measured maximum was reached
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnotatedPoint {
+    pub point: Point,
+    pub annotations: Vec<String>
+}
+
+/// Annotates the points closest to the measured `vmax` and `vmin` and the rising edges through the midpoint between them.
+/// Returns one `AnnotatedPoint` per point so their indices match, most without annotations. Unknown (NaN) measurements
+/// annotate nothing, and neither does the trigger as the header doesn't say where in the buffer it happened
+pub fn annotate_measurements(points: &[Point], measurements: &ProcessedMeasurements) -> Vec<AnnotatedPoint> {
+    let mut annotated: Vec<AnnotatedPoint> = points.iter().map(|point| AnnotatedPoint { point: *point, annotations: Vec::new() }).collect();
+    let closest = |voltage: f32| if voltage.is_nan() {
+        None
+    } else {
+        (0..points.len()).min_by(|a, b| (points[*a].voltage - voltage).abs().total_cmp(&(points[*b].voltage - voltage).abs()))
+    };
+
+    if let Some(index) = closest(measurements.vmax) {
+        annotated[index].annotations.push(format!("Vmax {}", format_voltage_display(measurements.vmax)));
+    }
+    if let Some(index) = closest(measurements.vmin) {
+        annotated[index].annotations.push(format!("Vmin {}", format_voltage_display(measurements.vmin)));
+    }
+
+    let midpoint_v = (measurements.vmax + measurements.vmin) / 2.0;
+
+    if !midpoint_v.is_nan() {
+        for index in 1..points.len() {
+            if points[index - 1].voltage < midpoint_v && points[index].voltage >= midpoint_v {
+                annotated[index].annotations.push("Rising edge".to_string());
+            }
+        }
+    }
+
+    annotated
+}
+
+/// Largest absolute voltage over the RMS of the voltages, about 1.414 for a sine and 1 for a symmetric square wave
+pub fn crest_factor(points: &[Point]) -> Option<f32> {
+    if points.is_empty() {
+        return None;
+    }
+
+    let peak_v = points.iter().map(|point| point.voltage.abs()).fold(0f32, f32::max);
+    let rms_v = (points.iter().map(|point| (point.voltage as f64).powi(2)).sum::<f64>() / points.len() as f64).sqrt();
+
+    (rms_v > 0.0).then(|| (peak_v as f64 / rms_v) as f32)
+}
+
+/// Ratio of the RMS of the harmonics of `fundamental_hz` to the fundamental in the Hann windowed FFT of the points,
+/// the power of every harmonic is summed over the bins its main lobe spreads over. `None` without power at the fundamental
+/// or with a fundamental below the one bin of a single period in the points
+pub fn total_harmonic_distortion(points: &[Point], fundamental_hz: f64, sample_rate_hz: f64) -> Option<f64> {
+    harmonic_powers(points, fundamental_hz, sample_rate_hz)
+        .filter(|powers| powers.fundamental > 0.0)
+        .map(|powers| (powers.harmonics / powers.fundamental).sqrt())
+}
+
+/// Power of the fundamental, of its harmonics and of the whole AC part of the points
+struct HarmonicPowers {
+    fundamental: f64,
+    harmonics: f64,
+    total: f64
+}
+
+fn harmonic_powers(points: &[Point], fundamental_hz: f64, sample_rate_hz: f64) -> Option<HarmonicPowers> {
+    let fundamental_bins = fundamental_hz * points.len() as f64 / sample_rate_hz;
+    // Finer fundamentals, and NaN from either frequency, would leave no bound on the harmonics below the Nyquist frequency
+    if points.len() < 4 || !fundamental_bins.is_finite() || fundamental_bins < 1.0 {
+        return None;
+    }
+
+    let mean = points.iter().map(|point| point.voltage as f64).sum::<f64>() / points.len() as f64;
+    let mut buffer: Vec<rustfft::num_complex::Complex<f64>> = points.iter()
+        .zip(hann_window(points.len()))
+        .map(|(point, weight)| rustfft::num_complex::Complex::new((point.voltage as f64 - mean) * weight, 0.0))
+        .collect();
+    rustfft::FftPlanner::new().plan_fft_forward(points.len()).process(&mut buffer);
+    let power: Vec<f64> = buffer[..points.len() / 2].iter().map(|bin| bin.norm_sqr()).collect();
+
+    // The main lobe of the Hann window is 2 bins to every side, narrower when the harmonics are closer than that
+    let half_width = (fundamental_bins / 2.0 - 0.5).floor().clamp(0.0, 2.0) as usize;
+    let near = |harmonic: usize| {
+        let center = (fundamental_bins * harmonic as f64).round() as usize;
+        (center < power.len()).then(|| power[center.saturating_sub(half_width)..(center + half_width + 1).min(power.len())].iter().sum::<f64>())
+    };
+    let fundamental = near(1)?;
+    let harmonics = (2..=(power.len() as f64 / fundamental_bins) as usize).filter_map(near).sum();
+
+    Some(HarmonicPowers { fundamental, harmonics, total: power.iter().sum() })
+}
+
+/// Recognizes the shape of the waveform of the points. Nearly flat points are DC and points with most of their AC power
+/// outside of the fundamental found by `period_estimation_fft` and its harmonics, or crossing their average far more often
+/// than the fundamental does, are noise. Less than 3 periods are unknown, the others are told apart by their crest factor, about 1.414 for a sine, 1 for a
+/// square and 1.732 for a triangle or a sawtooth, their total harmonic distortion and, for a sawtooth, by rising far longer
+/// than falling or the other way around
+pub fn detect_signal_type(points: &[Point], sample_rate_hz: f64) -> SignalType {
+    if points.is_empty() {
+        return SignalType::Dc;
+    }
+
+    let mean_v = points.iter().map(|point| point.voltage as f64).sum::<f64>() / points.len() as f64;
+    let centered: Vec<Point> = points.iter().map(|point| Point { voltage: (point.voltage as f64 - mean_v) as f32, ..*point }).collect();
+    let ac_rms_v = (centered.iter().map(|point| (point.voltage as f64).powi(2)).sum::<f64>() / points.len() as f64).sqrt();
+
+    if ac_rms_v <= DC_MAX_AC_TO_MEAN * mean_v.abs() || ac_rms_v == 0.0 {
+        return SignalType::Dc;
+    }
+
+    let fundamental_hz = match period_estimation_fft(points, sample_rate_hz) {
+        Some(period_s) => 1.0 / period_s,
+        None => return SignalType::Noise
+    };
+    // The hysteresis keeps small wiggles around the average from counting as crossings
+    let hysteresis_v = (ac_rms_v / 10.0) as f32;
+    let levels = digitize(&centered, hysteresis_v, -hysteresis_v);
+    let crossings = levels.windows(2).filter(|pair| !pair[0] && pair[1]).count() as f64;
+    let periods = fundamental_hz * points.len() as f64 / sample_rate_hz;
+
+    if crossings > 2.0 * periods + 1.0 {
+        return SignalType::Noise;
+    }
+    if periods < MIN_CLASSIFIED_PERIODS {
+        return SignalType::Unknown;
+    }
+
+    let powers = match harmonic_powers(points, fundamental_hz, sample_rate_hz) {
+        Some(powers) if powers.fundamental > 0.0 => powers,
+        _ => return SignalType::Noise
+    };
+
+    if (powers.fundamental + powers.harmonics) / powers.total < MIN_HARMONIC_POWER_FRACTION {
+        return SignalType::Noise;
+    }
+
+    let thd = (powers.harmonics / powers.fundamental).sqrt();
+    let crest = crest_factor(&centered).unwrap_or(0.0) as f64;
+    let (rising, falling) = centered.windows(2).fold((0usize, 0usize), |(rising, falling), pair| match pair[1].voltage.total_cmp(&pair[0].voltage) {
+        std::cmp::Ordering::Greater => (rising + 1, falling),
+        std::cmp::Ordering::Less => (rising, falling + 1),
+        std::cmp::Ordering::Equal => (rising, falling)
+    });
+    let asymmetry = (rising as f64 - falling as f64).abs() / (rising + falling).max(1) as f64;
+
+    match crest {
+        crest if thd < SINE_MAX_THD && (1.3..1.55).contains(&crest) => SignalType::Sine,
+        crest if thd > SQUARE_MIN_THD && crest < 1.2 => SignalType::Square,
+        crest if (1.55..2.0).contains(&crest) && asymmetry > SAWTOOTH_MIN_ASYMMETRY => SignalType::Sawtooth,
+        crest if (1.55..2.0).contains(&crest) && (SINE_MAX_THD..=SQUARE_MIN_THD).contains(&thd) => SignalType::Triangle,
+        _ => SignalType::Unknown
+    }
+}
+
+fn standard_deviation(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+
+    (values.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / values.len() as f32).sqrt()
+}
+
+/// Frequency from the period between the upward crossings of the average voltage, `None` when they cross it upwards
+/// less than twice
+pub fn frequency_zero_crossings(points: &[Point]) -> Option<f64> {
+    let mean_v = points.iter().map(|point| point.voltage as f64).sum::<f64>() / points.len() as f64;
+    let centered: Vec<Point> = points.iter().map(|point| Point { voltage: (point.voltage as f64 - mean_v) as f32, ..*point }).collect();
+    let crossings = rising_zero_crossings(&centered);
+
+    match crossings.len() {
+        0 | 1 => None,
+        count => Some((count - 1) as f64 / (crossings[count - 1] - crossings[0]))
+    }
+}
+
+/// The frequency of a channel measured 3 ways
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FrequencyComparison {
+    /// `None` for the 0 Hz the device reports when it found no frequency
+    pub device_hz: Option<f64>,
+    pub zero_crossing_hz: Option<f64>,
+    pub fft_hz: Option<f64>,
+    /// Whether all three are known and the lowest is within the tolerance of the highest
+    pub agree: bool
+}
+
+/// Compares the frequency `device_hz` reported by the device with the ones from the zero crossings and the FFT of the
+/// points, they agree when they are within a `tolerance` fraction of each other
+pub fn compare_frequencies(device_hz: u32, points: &[Point], sample_rate_hz: f64, tolerance: f32) -> FrequencyComparison {
+    let device_hz = (device_hz != 0).then_some(device_hz as f64);
+    let zero_crossing_hz = frequency_zero_crossings(points);
+    let fft_hz = period_estimation_fft(points, sample_rate_hz).map(|period_s| 1.0 / period_s);
+    let agree = match (device_hz, zero_crossing_hz, fft_hz) {
+        (Some(device), Some(zero_crossing), Some(fft)) => {
+            let (lowest, highest) = (device.min(zero_crossing).min(fft), device.max(zero_crossing).max(fft));
+            highest - lowest <= tolerance as f64 * highest
+        },
+        _ => false
+    };
+
+    FrequencyComparison { device_hz, zero_crossing_hz, fft_hz, agree }
+}
+
+/// Times of the upward zero crossings, linearly interpolated between the surrounding points
+fn rising_zero_crossings(points: &[Point]) -> Vec<f64> {
+    points.windows(2).filter_map(|pair| match pair {
+        [before, after] if before.voltage < 0.0 && after.voltage >= 0.0 => {
+            let fraction = (-before.voltage / (after.voltage - before.voltage)) as f64;
+            Some(before.time as f64 + fraction * (after.time - before.time) as f64)
+        },
+        _ => None
+    }).collect()
+}
+
+/// Least squares problem of `SineFit` with the parameters amplitude, frequency, phase and dc offset
+struct SineProblem<'a> {
+    points: &'a [Point],
+    params: nalgebra::Vector4<f64>
+}
+
+impl SineProblem<'_> {
+    fn angle(&self, point: &Point) -> f64 {
+        2.0 * std::f64::consts::PI * self.params[1] * point.time as f64 + self.params[2]
+    }
+}
+
+impl levenberg_marquardt::LeastSquaresProblem<f64, nalgebra::Dyn, nalgebra::U4> for SineProblem<'_> {
+    type ResidualStorage = nalgebra::storage::Owned<f64, nalgebra::Dyn>;
+    type JacobianStorage = nalgebra::storage::Owned<f64, nalgebra::Dyn, nalgebra::U4>;
+    type ParameterStorage = nalgebra::storage::Owned<f64, nalgebra::U4>;
+
+    fn set_params(&mut self, params: &nalgebra::Vector4<f64>) {
+        self.params = *params;
+    }
+
+    fn params(&self) -> nalgebra::Vector4<f64> {
+        self.params
+    }
+
+    fn residuals(&self) -> Option<nalgebra::DVector<f64>> {
+        Some(nalgebra::DVector::from_iterator(self.points.len(), self.points.iter()
+            .map(|point| self.params[0] * self.angle(point).sin() + self.params[3] - point.voltage as f64)))
+    }
+
+    fn jacobian(&self) -> Option<nalgebra::OMatrix<f64, nalgebra::Dyn, nalgebra::U4>> {
+        let mut jacobian = nalgebra::OMatrix::<f64, nalgebra::Dyn, nalgebra::U4>::zeros(self.points.len());
+
+        for (row, point) in self.points.iter().enumerate() {
+            let (sin, cos) = self.angle(point).sin_cos();
+            jacobian[(row, 0)] = sin;
+            jacobian[(row, 1)] = self.params[0] * cos * 2.0 * std::f64::consts::PI * point.time as f64;
+            jacobian[(row, 2)] = self.params[0] * cos;
+            jacobian[(row, 3)] = 1.0;
+        }
+
+        Some(jacobian)
+    }
+}
+
+/// Least squares sine fit refined with Levenberg-Marquardt from the peak of the FFT of the points.
+/// Returns `None` when the points cross their mean less than twice or when the fit doesn't converge.
+pub fn fit_sine(points: &[Point]) -> Option<SineFit> {
+    let mean = points.iter().map(|point| point.voltage as f64).sum::<f64>() / points.len() as f64;
+    let mean_crossings = points.windows(2)
+        .filter(|pair| (pair[0].voltage as f64 >= mean) != (pair[1].voltage as f64 >= mean))
+        .count();
+
+    if mean_crossings < 2 {
+        return None;
+    }
+
+    let duration = (points[points.len() - 1].time - points[0].time) as f64;
+    let sample_rate_hz = (points.len() - 1) as f64 / duration;
+    let mut buffer: Vec<rustfft::num_complex::Complex<f64>> = points.iter()
+        .map(|point| rustfft::num_complex::Complex::new(point.voltage as f64 - mean, 0.0))
+        .collect();
+    rustfft::FftPlanner::new().plan_fft_forward(buffer.len()).process(&mut buffer);
+
+    let peak = (1..=points.len() / 2).max_by(|a, b| buffer[*a].norm().total_cmp(&buffer[*b].norm()))?;
+    let frequency_hz = peak as f64 * sample_rate_hz / points.len() as f64;
+
+    // Projecting on the sine and the cosine of the peak frequency gives the initial amplitude and phase
+    let (sin_projection, cos_projection) = points.iter().fold((0.0, 0.0), |(sin_sum, cos_sum), point| {
+        let (sin, cos) = (2.0 * std::f64::consts::PI * frequency_hz * point.time as f64).sin_cos();
+        let voltage = point.voltage as f64 - mean;
+        (sin_sum + voltage * sin, cos_sum + voltage * cos)
+    });
+    let initial = nalgebra::Vector4::new(
+        2.0 * sin_projection.hypot(cos_projection) / points.len() as f64,
+        frequency_hz,
+        cos_projection.atan2(sin_projection),
+        mean
+    );
+
+    let (problem, report) = levenberg_marquardt::LevenbergMarquardt::new().minimize(SineProblem { points, params: initial });
+
+    if !report.termination.was_successful() {
+        return None;
+    }
+
+    let [amplitude_v, frequency_hz, phase_rad, dc_offset_v]: [f64; 4] = problem.params.into();
+    let (amplitude_v, phase_rad) = if amplitude_v < 0.0 { (-amplitude_v, phase_rad + std::f64::consts::PI) } else { (amplitude_v, phase_rad) };
+    let phase_rad = std::f64::consts::PI - (std::f64::consts::PI - phase_rad).rem_euclid(2.0 * std::f64::consts::PI);
+    let residual_sum = problem.residuals()?.norm_squared();
+    let total_sum: f64 = points.iter().map(|point| (point.voltage as f64 - mean).powi(2)).sum();
+
+    Some(SineFit {
+        amplitude_v,
+        frequency_hz,
+        phase_rad,
+        dc_offset_v,
+        r_squared: 1.0 - residual_sum / total_sum
+    })
+}
+
+/// Phase in degrees in `(-180, 180]` by which `ch2` lags `ch1`, using the period between the upward zero crossings of `ch1`.
+/// Returns `None` when either channel crosses zero upwards less than twice.
+pub fn phase_from_zero_crossings(ch1: &[Point], ch2: &[Point]) -> Option<f64> {
+    let (crossings1, crossings2) = (rising_zero_crossings(ch1), rising_zero_crossings(ch2));
+
+    if crossings1.len() < 2 || crossings2.len() < 2 {
+        return None;
+    }
+
+    let period = (crossings1[crossings1.len() - 1] - crossings1[0]) / (crossings1.len() - 1) as f64;
+    let phase = ((crossings2[0] - crossings1[0]) / period * 360.0).rem_euclid(360.0);
+
+    Some(if phase > 180.0 { phase - 360.0 } else { phase })
+}
+
+/// Magnitude squared coherence between two channels using Welch's method with Hann windowed segments of
+/// [`COHERENCE_SEGMENT_SIZE`] samples overlapping by half, shorter captures use a single segment of every sample.
+/// 1 means the channels are linearly related at that frequency and 0 that they are unrelated.
+pub fn coherence(ch1: &[Point], ch2: &[Point], sample_rate_hz: f64) -> Vec<(f64, f64)> {
+    let samples = ch1.len().min(ch2.len());
+    let segment_size = COHERENCE_SEGMENT_SIZE.min(samples);
+
+    if segment_size == 0 {
+        return Vec::new();
+    }
+
+    let window = hann_window(segment_size);
+    let fft = rustfft::FftPlanner::new().plan_fft_forward(segment_size);
+    let bins = segment_size / 2 + 1;
+    let (mut pxx, mut pyy) = (vec![0.0; bins], vec![0.0; bins]);
+    let mut pxy = vec![rustfft::num_complex::Complex::new(0.0, 0.0); bins];
+    let segment_spectrum = |points: &[Point]| {
+        let mut buffer: Vec<rustfft::num_complex::Complex<f64>> = points.iter()
+            .zip(&window)
+            .map(|(point, weight)| rustfft::num_complex::Complex::new(point.voltage as f64 * weight, 0.0))
+            .collect();
+        fft.process(&mut buffer);
+        buffer
+    };
+
+    for start in (0..=samples - segment_size).step_by((segment_size / 2).max(1)) {
+        let x = segment_spectrum(&ch1[start..start + segment_size]);
+        let y = segment_spectrum(&ch2[start..start + segment_size]);
+
+        for bin in 0..bins {
+            pxx[bin] += x[bin].norm_sqr();
+            pyy[bin] += y[bin].norm_sqr();
+            pxy[bin] += x[bin].conj() * y[bin];
+        }
+    }
+
+    (0..bins).map(|bin| {
+        let power = pxx[bin] * pyy[bin];
+        let coherence = if power > 0.0 { pxy[bin].norm_sqr() / power } else { 0.0 };
+
+        (bin as f64 * sample_rate_hz / segment_size as f64, coherence)
+    }).collect()
+}
+
+/// Transfer function of whatever sits between the `input` and `output_ch` channels, the ratio of their Hann windowed spectra.
+/// A broadband stimulus like a step or noise measures a whole Bode plot in one capture.
+/// Only the bins where the input is above [`TRANSFER_FUNCTION_NOISE_FLOOR_DB`] relative to its strongest bin are kept
+pub fn frequency_response(input: &[Point], output_ch: &[Point], sample_rate_hz: f64) -> TransferFunction {
+    let samples = input.len().min(output_ch.len());
+    let mut transfer_function = TransferFunction { frequency_bins: Vec::new(), magnitude_db: Vec::new(), phase_deg: Vec::new() };
+
+    if samples == 0 {
+        return transfer_function;
+    }
+
+    let window = hann_window(samples);
+    let fft = rustfft::FftPlanner::new().plan_fft_forward(samples);
+    let spectrum = |points: &[Point]| {
+        let mut buffer: Vec<rustfft::num_complex::Complex<f64>> = points.iter()
+            .zip(&window)
+            .map(|(point, weight)| rustfft::num_complex::Complex::new(point.voltage as f64 * weight, 0.0))
+            .collect();
+        fft.process(&mut buffer);
+        buffer.truncate(samples / 2 + 1);
+        buffer
+    };
+    let (x, y) = (spectrum(&input[..samples]), spectrum(&output_ch[..samples]));
+    let floor = x.iter().map(|bin| bin.norm()).fold(0.0, f64::max) * 10f64.powf(TRANSFER_FUNCTION_NOISE_FLOOR_DB / 20.0);
+
+    for (bin, (x, y)) in x.iter().zip(&y).enumerate() {
+        if x.norm() <= floor || x.norm() == 0.0 {
+            continue;
+        }
+
+        let ratio = y / x;
+        transfer_function.frequency_bins.push(bin as f64 * sample_rate_hz / samples as f64);
+        transfer_function.magnitude_db.push(20.0 * ratio.norm().max(f64::MIN_POSITIVE).log10());
+        transfer_function.phase_deg.push(ratio.arg().to_degrees());
+    }
+
+    transfer_function
+}
+
+/// The frequency in the file name of a capture of a sweep, like the `2.5kHz` of `sweep_2.5kHz.bin`.
+/// The `Hz` is case insensitive, the prefixes `m`, `k`, `M` and `G` scale it
+pub fn frequency_from_filename(path: &Path) -> Option<f64> {
+    // The prefix stays case sensitive, `m` and `M` are a billion apart
+    let pattern = regex::Regex::new(r"(\d+(?:\.\d+)?)([mkKMG]?)[Hh][Zz]").unwrap();
+    let captures = pattern.captures(path.file_stem()?.to_str()?)?;
+    let multiplier = match &captures[2] {
+        "m" => 1e-3,
+        "k" | "K" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        _ => 1.0
+    };
+
+    captures[1].parse::<f64>().ok().map(|frequency| frequency * multiplier)
+}
+
+/// Bode plot of whatever sits between the `input_channel` and `output_channel` of a sweep of captures, each at one frequency.
+/// The frequency of a capture is the one in its file name, see [`frequency_from_filename`], or the one the device measured on the
+/// input channel. The gain and phase are the ones of the [`frequency_response`] bin nearest to it, captures without a frequency
+/// or without any bin above the noise floor are skipped with a warning
+pub fn frequency_sweep_analysis(files: &[(PathBuf, Data)], input_channel: u8, output_channel: u8) -> FrequencySweepResult {
+    let mut points: Vec<BodePoint> = files.iter().filter_map(|(path, data)| {
+        let channel = |channel: u8| if channel == 1 { &data.channel1 } else { &data.channel2 };
+        let (input, output) = (channel(input_channel), channel(output_channel));
+        let frequency_hz = frequency_from_filename(path)
+            .or_else(|| (input.measurements.frequency > 0).then_some(input.measurements.frequency as f64));
+        let frequency_hz = match frequency_hz {
+            Some(frequency_hz) => frequency_hz,
+            None => {
+                log::warn!("{}: Skipping the capture, neither its name nor the device holds its frequency", path.display());
+                return None;
+            }
+        };
+        let response = frequency_response(&input.points, &output.points, sample_rate_hz(&data.time_scale) as f64);
+        let nearest = (0..response.frequency_bins.len())
+            .min_by(|a, b| (response.frequency_bins[*a] - frequency_hz).abs().total_cmp(&(response.frequency_bins[*b] - frequency_hz).abs()));
+
+        match nearest {
+            Some(bin) => Some(BodePoint { frequency_hz, gain_db: response.magnitude_db[bin], phase_deg: response.phase_deg[bin] }),
+            None => {
+                log::warn!("{}: Skipping the capture, the input channel {} is below the noise floor", path.display(), input_channel);
+                None
+            }
+        }
+    }).collect();
+
+    points.sort_by(|a, b| a.frequency_hz.total_cmp(&b.frequency_hz));
+    FrequencySweepResult { points }
+}
+
+/// Logic level of every point with Schmitt trigger hysteresis, high once above `high_v` until below `low_v`.
+/// Starts high when the first point is above `high_v`, all low with a warning when no point is beyond either threshold
+pub fn digitize(points: &[Point], high_v: f32, low_v: f32) -> Vec<bool> {
+    if !points.iter().any(|point| point.voltage > high_v || point.voltage < low_v) {
+        log::warn!("No point crosses the logic thresholds {} V and {} V", high_v, low_v);
+        return vec![false; points.len()];
+    }
+
+    let mut high = points.first().is_some_and(|point| point.voltage > high_v);
+
+    points.iter().map(|point| {
+        if point.voltage > high_v {
+            high = true;
+        } else if point.voltage < low_v {
+            high = false;
+        }
+        high
+    }).collect()
+}
+
+/// Packs the logic levels of the bits of a parallel bus into `ceil(bits.len() / 8)` bytes per sample, least significant bit first.
+/// Samples past the end of the shortest bit are dropped
+pub fn reconstruct_digital_word(bits: &[&[bool]]) -> Vec<u8> {
+    let samples = bits.iter().map(|bit| bit.len()).min().unwrap_or(0);
+    let bytes_per_word = bits.len().div_ceil(8);
+    let mut words = vec![0u8; samples * bytes_per_word];
+
+    for (bit_index, bit) in bits.iter().enumerate() {
+        for (sample, high) in bit[..samples].iter().enumerate() {
+            if *high {
+                words[sample * bytes_per_word + bit_index / 8] |= 1 << (bit_index % 8);
+            }
+        }
+    }
+
+    words
+}
+
+/// Adds or subtracts the voltages of the points with the same index, keeping the times of `ch1`
+pub fn merge_channels(ch1: &[Point], ch2: &[Point], merge: ChannelMerge) -> Result<Vec<Point>, FnirsiError> {
+    if ch1.len() != ch2.len() {
+        return Err(FnirsiError::SampleCountMismatch { channel1: ch1.len(), channel2: ch2.len() });
+    }
+
+    Ok(ch1.iter().zip(ch2).map(|(point1, point2)| Point {
+        time: point1.time,
+        voltage: match merge {
+            ChannelMerge::Sub => point1.voltage - point2.voltage,
+            ChannelMerge::Add => point1.voltage + point2.voltage
+        }
+    }).collect())
+}
+
+/// Channel 1 minus channel 2 as a channel of its own, from the voltages at the probe tips so differing probes cancel out.
+/// The scale starts from the finer of the two effective scales and takes coarser steps of the probe scales, up to 100x,
+/// until the difference fits on the `vertical_divisions` of the screen. The measurements are the ones of the points, the ones
+/// only the scope measures are zero
+pub fn differential_channel(ch1: &Channel, ch2: &Channel, vertical_divisions: f32) -> Result<Channel, FnirsiError> {
+    let points = merge_channels(&ch1.points, &ch2.points, ChannelMerge::Sub)?;
+    let reference = if ch1.effective_scale.get_scale() <= ch2.effective_scale.get_scale() { ch1.effective_scale } else { ch2.effective_scale };
+
+    let vmax = points.iter().map(|point| point.voltage).fold(f32::MIN, f32::max);
+    let vmin = points.iter().map(|point| point.voltage).fold(f32::MAX, f32::min);
+    let peak_v = vmax.abs().max(vmin.abs());
+    let mut scales: Vec<Scale<Volt>> = [Attenuation::OneX, Attenuation::TenX, Attenuation::OneHundredX].iter()
+        .flat_map(|attenuation| PROBE_SCALES.iter().filter(|entry| entry.is_valid).map(move |entry| entry.scale.with_attenuation(*attenuation)))
+        .filter(|scale| scale.get_scale() >= reference.get_scale())
+        .collect();
+    scales.sort_by(|a, b| a.get_scale().total_cmp(&b.get_scale()));
+    scales.dedup_by(|a, b| a.get_scale() == b.get_scale());
+    let scale = scales.iter()
+        .find(|scale| scale.get_scale() * vertical_divisions / 2.0 > peak_v)
+        .or(scales.last())
+        .copied()
+        .unwrap_or(reference);
+
+    let probe = ProbeConfig { scale, coupling: Coupling::DC, attenuation: Attenuation::OneX };
+    let count = points.len().max(1) as f32;
+    let vavg = points.iter().map(|point| point.voltage).sum::<f32>() / count;
+    let vrms = (points.iter().map(|point| point.voltage * point.voltage).sum::<f32>() / count).sqrt();
+    let measurements = ProcessedMeasurements {
+        vmax,
+        vmin,
+        vavg,
+        vrms,
+        vpp: vmax - vmin,
+        vp: peak_v,
+        frequency: 0,
+        frequency_estimated_hz: None,
+        estimated_period_ns: None,
+        frequency_comparison: None,
+        settling_time_s: None,
+        crest_factor: crest_factor(&points),
+        cycle_ns: RawMeasurement { raw: 0 },
+        time_plus_ns: RawMeasurement { raw: 0 },
+        time_minus_ns: RawMeasurement { raw: 0 },
+        duty_plus_percentage: 0,
+        duty_minus_percentage: 0,
+        human: None
+    };
+
+    Ok(Channel {
+        voltage_resolution_v: probe.voltage_resolution_v(),
+        effective_scale: probe.effective_scale(),
+        probe,
+        offset_v: 0.0,
+        is_trigger_source: false,
+        quality: channel_quality(&points, &scale, &measurements, vertical_divisions),
+        signal_class: classify_signal(&points),
+        signal_type: None,
+        measurements,
+        spectrogram: None,
+        extrapolated_points: None,
+        display_points: None,
+        division_markers: None,
+        points
+    })
+}
+
+/// Corrects every voltage as `v * gain + offset_v`, with coefficients found by measuring a known reference voltage
+pub fn apply_calibration(points: &[Point], gain: f32, offset_v: f32) -> Vec<Point> {
+    points.iter().map(|point| Point { time: point.time, voltage: point.voltage * gain + offset_v }).collect()
+}
+
+/// Reads the calibration of channel 1 and 2 from `[ch1]` and `[ch2]` tables holding a `gain` and an `offset_v`
+pub fn read_calibration_file(path: &Path) -> Result<[Calibration; 2], FnirsiError> {
+    let file: CalibrationFile = toml::from_str(&std::fs::read_to_string(path)?)?;
+
+    Ok([file.ch1.unwrap_or_default(), file.ch2.unwrap_or_default()])
+}
+
+/// Peak following envelope, jumping up to the absolute voltage whenever it is above the envelope
+/// and otherwise shrinking by `decay_rate` of its value every sample
+pub fn envelope_detection(points: &[Point], decay_rate: f32) -> Vec<Point> {
+    let mut envelope = 0f32;
+
+    points.iter().map(|point| {
+        envelope = point.voltage.abs().max(envelope * (1.0 - decay_rate));
+        Point { time: point.time, voltage: envelope }
+    }).collect()
+}
+
+/// RMS of the `window` samples up to and including every point, fewer at the start of the capture
+pub fn rms_envelope(points: &[Point], window: usize) -> Vec<Point> {
+    let window = window.max(1);
+    let mut sum_of_squares = 0f64;
+
+    points.iter().enumerate().map(|(index, point)| {
+        sum_of_squares += (point.voltage as f64).powi(2);
+
+        if index >= window {
+            sum_of_squares -= (points[index - window].voltage as f64).powi(2);
+        }
+
+        let samples = (index + 1).min(window);
+        Point { time: point.time, voltage: (sum_of_squares.max(0.0) / samples as f64).sqrt() as f32 }
+    }).collect()
+}
+
+/// One point per `factor` consecutive points with their average voltage at the time of the center one, points after the last
+/// whole group are dropped. Uncorrelated noise shrinks by `sqrt(factor)`, adding `0.5 * log2(factor)` bits of resolution
+pub fn oversample_average(points: &[Point], factor: usize) -> Vec<Point> {
+    if factor <= 1 {
+        return points.to_vec();
+    }
+
+    points.chunks_exact(factor).map(|group| Point {
+        time: group[factor / 2].time,
+        voltage: (group.iter().map(|point| point.voltage as f64).sum::<f64>() / factor as f64) as f32
+    }).collect()
+}
+
+/// Resamples the points from `current_sample_rate_hz` to `target_sample_rate_hz` in the frequency domain, which is the
+/// sinc interpolation of a periodic signal. Downsampling drops the bins above the nyquist limit of the target rate,
+/// the ideal anti-aliasing low-pass, and upsampling fills the new bins with zeros. The capture is treated as one period
+/// so a signal that doesn't start and end on the same level rings near both ends
+pub fn resample(points: &[Point], target_sample_rate_hz: f64, current_sample_rate_hz: f64) -> Vec<Point> {
+    let valid_rate = |rate_hz: f64| rate_hz.is_finite() && rate_hz > 0.0;
+
+    if points.len() < 2 || !valid_rate(target_sample_rate_hz) || !valid_rate(current_sample_rate_hz) || target_sample_rate_hz == current_sample_rate_hz {
+        return points.to_vec();
+    }
+
+    let input_len = points.len();
+    let output_len = (input_len as f64 * target_sample_rate_hz / current_sample_rate_hz).round() as usize;
+
+    if output_len == 0 {
+        return Vec::new();
+    }
+
+    let mut planner = realfft::RealFftPlanner::<f64>::new();
+    let forward = planner.plan_fft_forward(input_len);
+    let inverse = planner.plan_fft_inverse(output_len);
+    let mut input: Vec<f64> = points.iter().map(|point| point.voltage as f64).collect();
+    let mut spectrum = forward.make_output_vec();
+    let mut resampled_spectrum = inverse.make_input_vec();
+    let mut output = inverse.make_output_vec();
+
+    forward.process(&mut input, &mut spectrum).expect("the buffers are made by the plan");
+
+    let bins = spectrum.len().min(resampled_spectrum.len());
+    resampled_spectrum[..bins].copy_from_slice(&spectrum[..bins]);
+    // Only a real signal makes it through the inverse, which has no imaginary part at 0 Hz and at an even nyquist bin
+    resampled_spectrum[0].im = 0.0;
+    if output_len.is_multiple_of(2) {
+        resampled_spectrum[output_len / 2].im = 0.0;
+    }
+
+    inverse.process(&mut resampled_spectrum, &mut output).expect("the buffers are made by the plan");
+
+    let start_s = points[0].time as f64;
+
+    output.iter().enumerate().map(|(index, voltage)| Point {
+        time: (start_s + index as f64 / target_sample_rate_hz) as f32,
+        voltage: (voltage / input_len as f64) as f32
+    }).collect()
+}
+
+/// Reduces the points to `output_samples` windows of consecutive points keeping the lowest and highest voltage of each,
+/// like the display of a scope, so a peak as narrow as a single point still shows where decimation would skip it.
+/// Every point is its own window when there are fewer than `output_samples`
+pub fn downsample_minmax(points: &[Point], output_samples: usize) -> Vec<MinMaxPoint> {
+    let windows = output_samples.min(points.len());
+
+    (0..windows).map(|window| {
+        let window_points = &points[window * points.len() / windows..(window + 1) * points.len() / windows];
+
+        MinMaxPoint {
+            time_s: window_points[0].time as f64,
+            min_v: window_points.iter().map(|point| point.voltage).fold(f32::INFINITY, f32::min),
+            max_v: window_points.iter().map(|point| point.voltage).fold(f32::NEG_INFINITY, f32::max)
+        }
+    }).collect()
+}
+
+/// Upsamples the points by `factor`, adding `factor - 1` points between every pair of neighbours at evenly spaced times.
+/// Cubic interpolation follows the Catmull-Rom spline through the points, the first and last points are their own neighbours
+pub fn interpolate(points: &[Point], method: InterpolationMethod, factor: usize) -> Vec<Point> {
+    if factor <= 1 || points.len() < 2 {
+        return points.to_vec();
+    }
+
+    let mut interpolated = Vec::with_capacity((points.len() - 1) * factor + 1);
+
+    for index in 0..points.len() - 1 {
+        let (start, end) = (points[index], points[index + 1]);
+        let before = points[index.saturating_sub(1)].voltage;
+        let after = points[(index + 2).min(points.len() - 1)].voltage;
+
+        for step in 0..factor {
+            let t = step as f32 / factor as f32;
+            let voltage = match method {
+                InterpolationMethod::Linear => start.voltage + (end.voltage - start.voltage) * t,
+                InterpolationMethod::Cubic => 0.5 * (
+                    2.0 * start.voltage
+                        + (end.voltage - before) * t
+                        + (2.0 * before - 5.0 * start.voltage + 4.0 * end.voltage - after) * t * t
+                        + (3.0 * start.voltage - before - 3.0 * end.voltage + after) * t * t * t
+                )
+            };
+
+            interpolated.push(Point { time: start.time + (end.time - start.time) * t, voltage });
+        }
+    }
+
+    interpolated.push(points[points.len() - 1]);
+    interpolated
+}
+
+/// Voltages of the consecutive `period_samples` long periods, one row per period, samples after the last whole period are dropped
+pub fn fold_waveform(points: &[Point], period_samples: usize) -> Vec<Vec<f32>> {
+    if period_samples == 0 {
+        return Vec::new();
+    }
+
+    points.chunks_exact(period_samples)
+        .map(|period| period.iter().map(|point| point.voltage).collect())
+        .collect()
+}
+
+/// Average of the folded periods, noise uncorrelated with the period shrinks with the square root of the number of periods.
+/// Empty when the points don't span a whole period
+pub fn phase_fold_average(points: &[Point], period_samples: usize) -> Vec<f32> {
+    let periods = fold_waveform(points, period_samples);
+    let mut sums = vec![0f64; if periods.is_empty() { 0 } else { period_samples }];
+
+    for period in &periods {
+        sums.iter_mut().zip(period).for_each(|(sum, voltage)| *sum += *voltage as f64);
+    }
+
+    sums.into_iter().map(|sum| (sum / periods.len() as f64) as f32).collect()
+}
+
+/// Scales the voltages linearly so the lowest maps to -1 and the highest to 1, the points are returned unchanged when all voltages are equal
+pub fn normalize_points(points: &[Point]) -> Vec<Point> {
+    let min = points.iter().map(|point| point.voltage).fold(f32::INFINITY, f32::min);
+    let max = points.iter().map(|point| point.voltage).fold(f32::NEG_INFINITY, f32::max);
+
+    if min >= max {
+        return points.to_vec();
+    }
+
+    points.iter().map(|point| Point { time: point.time, voltage: 2.0 * (point.voltage - min) / (max - min) - 1.0 }).collect()
+}
+
+/// Divides the voltages by `reference_v` so ±1 corresponds to ±`reference_v`, the points are returned unchanged for a zero reference
+pub fn normalize_points_symmetric(points: &[Point], reference_v: f32) -> Vec<Point> {
+    if reference_v == 0.0 {
+        return points.to_vec();
+    }
+
+    points.iter().map(|point| Point { time: point.time, voltage: point.voltage / reference_v }).collect()
+}
+
+pub fn hann_window(size: usize) -> Vec<f64> {
+    if size < 2 {
+        return vec![1.0; size];
+    }
+
+    (0..size).map(|index| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * index as f64 / (size - 1) as f64).cos()).collect()
+}
+
+/// Peaks closer than this to the median magnitude of the spectrum aren't taken as the fundamental
+const MIN_FFT_PEAK_ABOVE_NOISE_DB: f64 = 6.0;
+
+/// Period in seconds of the strongest non DC bin of the Hann windowed FFT of the points, refined between the bins
+/// with Quinn's second estimator. Returns `None` when the peak is within 6 dB of the noise floor, the median magnitude.
+pub fn period_estimation_fft(points: &[Point], sample_rate_hz: f64) -> Option<f64> {
+    if points.len() < 4 {
+        return None;
+    }
+
+    // Without the mean the window doesn't spread the DC level into the first bins
+    let mean = points.iter().map(|point| point.voltage as f64).sum::<f64>() / points.len() as f64;
+    let fft = rustfft::FftPlanner::new().plan_fft_forward(points.len());
+    let spectrum = |weights: &[f64]| {
+        let mut buffer: Vec<rustfft::num_complex::Complex<f64>> = points.iter()
+            .zip(weights)
+            .map(|(point, weight)| rustfft::num_complex::Complex::new((point.voltage as f64 - mean) * weight, 0.0))
+            .collect();
+        fft.process(&mut buffer);
+        // The bins above the nyquist limit mirror the ones below
+        buffer.truncate(points.len() / 2);
+        buffer
+    };
+    let windowed = spectrum(&hann_window(points.len()));
+
+    // The last bin is left out for the right neighbour of the refinement
+    let peak = (1..windowed.len() - 1).max_by(|a, b| windowed[*a].norm().total_cmp(&windowed[*b].norm()))?;
+
+    let mut magnitudes: Vec<f64> = windowed[1..].iter().map(|bin| bin.norm()).collect();
+    magnitudes.sort_by(f64::total_cmp);
+    let noise_floor = magnitudes[magnitudes.len() / 2];
+
+    if windowed[peak].norm() <= noise_floor * 10f64.powf(MIN_FFT_PEAK_ABOVE_NOISE_DB / 20.0) {
+        return None;
+    }
+
+    // Quinn's second estimator assumes the rectangular window, it's off by up to a percent on the Hann one,
+    // so the peak found on the windowed spectrum is refined on the unwindowed one
+    let bins = spectrum(&vec![1.0; points.len()]);
+    let tau = |x: f64| {
+        let root = (2.0f64 / 3.0).sqrt();
+        0.25 * (3.0 * x * x + 6.0 * x + 1.0).ln() - 6f64.sqrt() / 24.0 * ((x + 1.0 - root) / (x + 1.0 + root)).ln()
+    };
+    let ratio = |neighbour: usize| (bins[neighbour] / bins[peak]).re;
+    let (ratio_plus, ratio_minus) = (ratio(peak + 1), ratio(peak - 1));
+    let delta_plus = -ratio_plus / (1.0 - ratio_plus);
+    let delta_minus = ratio_minus / (1.0 - ratio_minus);
+    let delta = (delta_plus + delta_minus) / 2.0 + tau(delta_plus * delta_plus) - tau(delta_minus * delta_minus);
+
+    let frequency_hz = (peak as f64 + delta) * sample_rate_hz / points.len() as f64;
+    (frequency_hz > 0.0).then(|| 1.0 / frequency_hz)
+}
+
+/// Hann windowed FFTs of `window_size` samples every `hop_size` samples, in dB relative to a full scale sine of 1V.
+/// Only the non negative frequencies up to the nyquist limit are kept.
+pub fn spectrogram(points: &[Point], sample_rate_hz: f64, window_size: usize, hop_size: usize) -> Spectrogram {
+    let window = hann_window(window_size);
+    let window_gain: f64 = window.iter().sum::<f64>() / 2.0;
+    let fft = rustfft::FftPlanner::new().plan_fft_forward(window_size);
+    let start_time = points.first().map(|point| point.time as f64).unwrap_or_default();
+    let mut spectrogram = Spectrogram {
+        time_bins: Vec::new(),
+        frequency_bins: (0..=window_size / 2).map(|bin| bin as f64 * sample_rate_hz / window_size as f64).collect(),
+        magnitude_db: Vec::new()
+    };
+
+    if window_size == 0 {
+        return spectrogram;
+    }
+
+    for start in (0..points.len().saturating_sub(window_size - 1)).step_by(hop_size.max(1)) {
+        let mut buffer: Vec<rustfft::num_complex::Complex<f64>> = points[start..start + window_size].iter()
+            .zip(&window)
+            .map(|(point, weight)| rustfft::num_complex::Complex::new(point.voltage as f64 * weight, 0.0))
+            .collect();
+        fft.process(&mut buffer);
+
+        spectrogram.time_bins.push(start_time + (start as f64 + window_size as f64 / 2.0) / sample_rate_hz);
+        spectrogram.magnitude_db.push(buffer[..=window_size / 2].iter()
+            .map(|value| 20.0 * (value.norm() / window_gain).max(1e-12).log10())
+            .collect());
+    }
+
+    spectrogram
+}
+
+/// The voltage halfway between the lowest and highest point
+pub fn midpoint_v(points: &[Point]) -> Option<f32> {
+    let min = points.iter().map(|point| point.voltage).reduce(f32::min)?;
+    let max = points.iter().map(|point| point.voltage).reduce(f32::max)?;
+
+    Some((min + max) / 2.0)
+}
+
+/// Voltage of a vertical cursor at `time_s`, linearly interpolated between the samples around it.
+/// Returns `None` outside of the capture, the points have to be sorted by time.
+pub fn interpolate_voltage_at_time(points: &[Point], time_s: f64) -> Option<f32> {
+    let (first, last) = (points.first()?, points.last()?);
+
+    if time_s < first.time as f64 || time_s > last.time as f64 {
+        return None;
+    }
+
+    // Index of the first point after the cursor, the one before it is at or before the cursor
+    let after = points.partition_point(|point| point.time as f64 <= time_s);
+
+    if after == points.len() {
+        return Some(last.voltage);
+    }
+
+    let (before, after) = (points[after - 1], points[after]);
+    let fraction = (time_s - before.time as f64) / (after.time - before.time) as f64;
+
+    Some((before.voltage as f64 + fraction * (after.voltage - before.voltage) as f64) as f32)
+}
+
+/// Time of a horizontal cursor at `voltage_v`, the first time from sample `after_sample` on that the signal reaches it,
+/// linearly interpolated between the samples around it. Returns `None` when the signal doesn't reach it.
+pub fn interpolate_time_at_voltage(points: &[Point], voltage_v: f32, after_sample: usize) -> Option<f64> {
+    if points.get(after_sample)?.voltage == voltage_v {
+        return Some(points[after_sample].time as f64);
+    }
+
+    points[after_sample..].windows(2).find_map(|pair| {
+        let (before, after) = (pair[0], pair[1]);
+
+        if (before.voltage < voltage_v) == (after.voltage < voltage_v) && after.voltage != voltage_v {
+            return None;
+        }
+
+        let fraction = ((voltage_v - before.voltage) / (after.voltage - before.voltage)) as f64;
+        Some(before.time as f64 + fraction * (after.time - before.time) as f64)
+    })
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SamplePoint {
+    pub time: f32,
+    pub voltage: f32,
+    /// Predicted rather than measured
+    pub extrapolated: bool
+}
+
+/// `File` with every sample buffer encoded as base64 of its little endian bytes, a fraction of the size of number arrays in json
+#[derive(Debug, Serialize)]
+pub struct Base64File<'a> {
+    pub header: &'a Header,
+    pub channel11: String,
+    pub channel21: String,
+    pub channel12: String,
+    pub channel22: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub channel31: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub channel41: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub channel32: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub channel42: String
+}
+
+impl <'a> From<&'a File> for Base64File<'a> {
+    fn from(file: &'a File) -> Self {
+        Base64File {
+            header: &file.header,
+            channel11: encode_samples_base64(&file.channel11),
+            channel21: encode_samples_base64(&file.channel21),
+            channel12: encode_samples_base64(&file.channel12),
+            channel22: encode_samples_base64(&file.channel22),
+            channel31: encode_samples_base64(&file.channel31),
+            channel41: encode_samples_base64(&file.channel41),
+            channel32: encode_samples_base64(&file.channel32),
+            channel42: encode_samples_base64(&file.channel42)
+        }
+    }
+}
+
+/// A sample buffer of a capture where it was read from
+#[derive(Debug, Serialize)]
+pub struct RawBuffer<'a> {
+    pub name: &'static str,
+    /// Bytes from the start of the capture
+    pub offset: u64,
+    pub samples: &'a [u16]
+}
+
+/// Every non empty sample buffer of a capture in file order, to look at them one by one while reverse engineering
+pub fn split_buffers(context: &CaptureContext) -> Vec<RawBuffer<'_>> {
+    let file = &context.file;
+    let buffers = [
+        ("channel11", &file.channel11), ("channel21", &file.channel21), ("channel12", &file.channel12), ("channel22", &file.channel22),
+        ("channel31", &file.channel31), ("channel41", &file.channel41), ("channel32", &file.channel32), ("channel42", &file.channel42)
+    ];
+    let mut offset = context.model.layout().channel_data_offset;
+
+    buffers.iter()
+        .filter(|(_, samples)| !samples.is_empty())
+        .map(|(name, samples)| {
+            let buffer = RawBuffer { name, offset, samples };
+            offset += (samples.len() * std::mem::size_of::<u16>()) as u64;
+            buffer
+        })
+        .collect()
+}
+
+/// Indices of the points closest to the vertical grid lines of the screen, one every `points_per_division` points
+/// starting with the first, which is `DIVISION_POINTS` for the points of the samples of a capture
+pub fn division_markers(num_points: usize, points_per_division: f64) -> Vec<usize> {
+    if points_per_division <= 0.0 || points_per_division.is_nan() {
+        return Vec::new();
+    }
+
+    (0..).map(|division| (division as f64 * points_per_division).round() as usize)
+        .take_while(|index| *index < num_points)
+        .collect()
+}
+
+pub fn encode_samples_base64(samples: &[u16]) -> String {
+    use base64::Engine;
+
+    let bytes: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Builds the binary representation of a capture, the padding between the known fields is filled with zeros
+pub struct FileBuilder {
+    file: File,
+    /// The last scale set that has no index in its table, `build` fails on it
+    unknown_scale: Option<String>
+}
+
+impl Default for FileBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileBuilder {
+    pub fn new() -> Self {
+        FileBuilder {
+            file: File {
+                channel11: vec![0; CHANNEL_SAMPLES],
+                channel21: vec![0; CHANNEL_SAMPLES],
+                channel12: vec![0; ZOOM_SAMPLES],
+                channel22: vec![0; ZOOM_SAMPLES],
+                header: Header {
+                    channel1_offset: ADC_MIDPOINT,
+                    channel2_offset: ADC_MIDPOINT,
+                    ..Header::default()
+                },
+                ..File::default()
+            },
+            unknown_scale: None
+        }
+    }
+
+    pub fn channel1_scale(mut self, scale: Scale<Volt>) -> Self {
+        match probe_scale_to_index(&scale) {
+            Some(index) => self.file.header.channel1_scale = index,
+            None => self.unknown_scale = Some(scale.to_string())
+        }
+        self
+    }
+
+    pub fn channel1_coupling(mut self, coupling: Coupling) -> Self {
+        self.file.header.channel1_coupling = coupling as u16;
+        self
+    }
+
+    pub fn channel1_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.file.header.channel1_probe = attenuation as u16;
+        self
+    }
+
+    pub fn channel1_offset(mut self, offset: u16) -> Self {
+        self.file.header.channel1_offset = offset;
+        self
+    }
+
+    pub fn channel1_measurements(mut self, measurements: Measurements) -> Self {
+        self.file.header.channel1_measurements = measurements;
+        self
+    }
+
+    pub fn channel1_data(mut self, samples: Vec<u16>) -> Self {
+        self.file.channel11 = samples;
+        self
+    }
+
+    pub fn channel1_zoom_data(mut self, samples: Vec<u16>) -> Self {
+        self.file.channel12 = samples;
+        self
+    }
+
+    pub fn channel2_scale(mut self, scale: Scale<Volt>) -> Self {
+        match probe_scale_to_index(&scale) {
+            Some(index) => self.file.header.channel2_scale = index,
+            None => self.unknown_scale = Some(scale.to_string())
+        }
+        self
+    }
+
+    pub fn channel2_coupling(mut self, coupling: Coupling) -> Self {
+        self.file.header.channel2_coupling = coupling as u16;
+        self
+    }
+
+    pub fn channel2_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.file.header.channel2_probe = attenuation as u16;
+        self
+    }
+
+    pub fn channel2_offset(mut self, offset: u16) -> Self {
+        self.file.header.channel2_offset = offset;
+        self
+    }
+
+    pub fn channel2_measurements(mut self, measurements: Measurements) -> Self {
+        self.file.header.channel2_measurements = measurements;
+        self
+    }
+
+    pub fn channel2_data(mut self, samples: Vec<u16>) -> Self {
+        self.file.channel21 = samples;
+        self
+    }
+
+    pub fn channel2_zoom_data(mut self, samples: Vec<u16>) -> Self {
+        self.file.channel22 = samples;
+        self
+    }
+
+    pub fn time_scale(mut self, scale: Scale<Second>) -> Self {
+        match time_scale_to_index(&scale) {
+            Some(index) => self.file.header.time_scale = index,
+            None => self.unknown_scale = Some(scale.to_string())
+        }
+        self
+    }
+
+    pub fn scroll_speed(mut self, scroll_speed: ScrollSpeed) -> Self {
+        self.file.header.scroll_speed = scroll_speed as u16;
+        self
+    }
+
+    pub fn trigger_type(mut self, trigger_type: TriggerType) -> Self {
+        self.file.header.trigger_type = trigger_type as u16;
+        self
+    }
+
+    pub fn trigger_edge(mut self, edge: TriggerEdge) -> Self {
+        self.file.header.trigger_edge = edge as u16;
+        self
+    }
+
+    pub fn trigger_channel(mut self, channel: TriggerChannel) -> Self {
+        self.file.header.trigger_channel = channel as u16;
+        self
+    }
+
+    pub fn trigger_50(mut self, trigger_50: Trigger50) -> Self {
+        self.file.header.trigger_50 = trigger_50 as u16;
+        self
+    }
+
+    pub fn screen_brightness(mut self, brightness: u16) -> Self {
+        self.file.header.screen_brightness = brightness;
+        self
+    }
+
+    pub fn grid_brightness(mut self, brightness: u16) -> Self {
+        self.file.header.grid_brightness = brightness;
+        self
+    }
+
+    /// Writes the fields at the offsets the `BinRead` layout of `File` reads them from, a `ScaleNotInTable` error
+    /// when a scale was set that the header can't hold
+    pub fn build(&self) -> Result<Vec<u8>, FnirsiError> {
+        if let Some(scale) = &self.unknown_scale {
+            return Err(FnirsiError::ScaleNotInTable(scale.clone()));
+        }
+
+        let header = &self.file.header;
+        let mut buffer = Cursor::new(Vec::new());
+        let skip = |buffer: &mut Cursor<Vec<u8>>, bytes: i64| { buffer.seek(SeekFrom::Current(bytes)).unwrap(); };
+        let put = |buffer: &mut Cursor<Vec<u8>>, value: u16| buffer.write_all(&value.to_le_bytes()).unwrap();
+        let put_measurements = |buffer: &mut Cursor<Vec<u8>>, measurements: &Measurements| {
+            for value in [measurements.vmax, measurements.vmin, measurements.vavg, measurements.vrms, measurements.vpp, measurements.vp] {
+                buffer.seek(SeekFrom::Current(2)).unwrap();
+                put(buffer, value);
+            }
+
+            put(buffer, measurements.frequency_high);
+            put(buffer, measurements.frequency_low);
+
+            for value in [measurements.cycle_ns, measurements.time_plus_ns, measurements.time_minus_ns, measurements.duty_plus_percentage, measurements.duty_minus_percentage] {
+                buffer.seek(SeekFrom::Current(2)).unwrap();
+                put(buffer, value);
+            }
+        };
+
+        skip(&mut buffer, 4);
+        put(&mut buffer, header.channel1_scale);
+        skip(&mut buffer, 2);
+        put(&mut buffer, header.channel1_coupling);
+        put(&mut buffer, header.channel1_probe);
+        skip(&mut buffer, 2);
+        put(&mut buffer, header.channel2_scale);
+        skip(&mut buffer, 2);
+        put(&mut buffer, header.channel2_coupling);
+        put(&mut buffer, header.channel2_probe);
+
+        for value in [header.time_scale, header.scroll_speed, header.trigger_type, header.trigger_edge, header.trigger_channel] {
+            put(&mut buffer, value);
+        }
+
+        skip(&mut buffer, 52);
+        put(&mut buffer, header.channel1_offset);
+        put(&mut buffer, header.channel2_offset);
+        skip(&mut buffer, 32);
+        put(&mut buffer, header.screen_brightness);
+        put(&mut buffer, header.grid_brightness);
+        put(&mut buffer, header.trigger_50);
+
+        buffer.set_position(CHANNEL1_MEASUREMENTS_OFFSET);
+        put_measurements(&mut buffer, &header.channel1_measurements);
+        buffer.set_position(CHANNEL2_MEASUREMENTS_OFFSET);
+        put_measurements(&mut buffer, &header.channel2_measurements);
+        buffer.set_position(CHANNEL_DATA_OFFSET);
+
+        let file = &self.file;
+
+        for samples in [&file.channel11, &file.channel21, &file.channel12, &file.channel22, &file.channel31, &file.channel41, &file.channel32, &file.channel42] {
+            for sample in samples {
+                put(&mut buffer, *sample);
+            }
+        }
+
+        Ok(buffer.into_inner())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProcessedMeasurements {
+    pub vmax: f32,
+    pub vmin: f32,
+    pub vavg: f32,
+    pub vrms: f32,
+    pub vpp: f32,
+    pub vp: f32,
+    pub frequency: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_estimated_hz: Option<f32>,
+    /// Period from the peak of the FFT of the points, `None` without a clear fundamental
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_period_ns: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_comparison: Option<FrequencyComparison>,
+    /// Only known for steps
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settling_time_s: Option<f32>,
+    /// Peak over RMS of the points, `None` when the RMS is zero
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crest_factor: Option<f32>,
+    pub cycle_ns: RawMeasurement,
+    pub time_plus_ns: RawMeasurement,
+    pub time_minus_ns: RawMeasurement,
+    pub duty_plus_percentage: u16,
+    pub duty_minus_percentage: u16,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub human: Option<HumanMeasurements>
+}
+
+/// A measurement whose `MeasurementEncoding` is unknown, as stored in the file
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RawMeasurement {
+    pub raw: u16
+}
+
+#[derive(Debug, Serialize)]
+pub struct HumanMeasurements {
+    pub vmax_display: String,
+    pub vmin_display: String,
+    pub vavg_display: String,
+    pub vrms_display: String,
+    pub vpp_display: String,
+    pub vp_display: String
+}
+
@@ -0,0 +1,322 @@
+//! Runs the analyses on synthetic points
+
+use std::convert::TryFrom;
+
+use fnirsi::*;
+
+fn sine(frequency_hz: f64, sample_rate_hz: f64, samples: usize) -> Vec<Point> {
+    (0..samples)
+        .map(|index| {
+            let time = index as f64 / sample_rate_hz;
+            Point { time: time as f32, voltage: (2.0 * std::f64::consts::PI * frequency_hz * time).sin() as f32 }
+        })
+        .collect()
+}
+
+#[test]
+fn bins_the_two_levels_of_a_square() {
+    let square: Vec<Point> = (0..1500).map(|index| Point { time: index as f32 * 1e-5, voltage: if index / 50 % 2 == 0 { 3.3 } else { 0.0 } }).collect();
+    let bins = voltage_histogram(&square, 10);
+    let filled = bins.iter().filter(|bin| bin.count > 0).collect::<Vec<_>>();
+
+    assert_eq!(bins.len(), 10);
+    assert_eq!(filled.len(), 2);
+    assert_eq!((filled[0].count, filled[1].count), (750, 750));
+    assert_eq!(bins[0].min_v, 0.0);
+    assert!((bins[9].max_v - 3.3).abs() < 1e-5);
+}
+
+#[test]
+fn merges_the_channels_point_by_point() {
+    let constant = |voltage: f32| (0..100).map(|index| Point { time: index as f32 * 1e-5, voltage }).collect::<Vec<_>>();
+    let difference = merge_channels(&constant(3.0), &constant(1.25), ChannelMerge::Sub).unwrap();
+    let sum = merge_channels(&constant(3.0), &constant(1.25), ChannelMerge::Add).unwrap();
+
+    assert_eq!(difference.len(), 100);
+    assert!(difference.iter().all(|point| point.voltage == 1.75));
+    assert!(sum.iter().all(|point| point.voltage == 4.25));
+    assert_eq!(difference[99].time, constant(0.0)[99].time);
+    assert!(matches!(
+        merge_channels(&constant(3.0), &constant(1.25)[..50], ChannelMerge::Sub),
+        Err(FnirsiError::SampleCountMismatch { channel1: 100, channel2: 50 })
+    ));
+}
+
+#[test]
+fn bins_the_pulse_widths_of_a_constant_duty_cycle() {
+    // 30 samples high out of every 100
+    let pulses: Vec<Point> = (0..1500).map(|index| Point { time: index as f32 * 1e-5, voltage: if index % 100 < 30 { 1.0 } else { 0.0 } }).collect();
+    let (high, low) = time_histogram(&pulses, 0.5, 8);
+
+    assert_eq!(high.len(), 8);
+    // The pulses cut off at either end of the capture aren't counted
+    assert_eq!(high[0].count, 14);
+    assert_eq!(low[0].count, 14);
+    assert!(high[1..].iter().chain(&low[1..]).all(|bin| bin.count == 0));
+    assert!((high[0].min_v - 30e-5).abs() < 1e-8 && (low[0].min_v - 70e-5).abs() < 1e-8, "{:?} {:?}", high[0], low[0]);
+}
+
+#[test]
+fn finds_a_one_sample_spike() {
+    let flat: Vec<Point> = (0..1500).map(|index| Point { time: index as f32 * 1e-5, voltage: 0.0 }).collect();
+    let mut spiked = flat.clone();
+    spiked[333].voltage = 3.0;
+    let mut points = sine(1000.0, 100e3, 1500);
+    points[333].voltage = 3.0;
+
+    assert_eq!(find_glitches(&spiked, 2, 1.5), [GlitchEvent { start_sample: 333, end_sample: 333, peak_voltage_v: 3.0 }]);
+    assert!(find_glitches(&flat, 2, 1.5).is_empty());
+    assert_eq!(find_glitches(&points, 2, 1.5), [GlitchEvent { start_sample: 333, end_sample: 333, peak_voltage_v: 3.0 }]);
+    // Every half period of the sine is wider than the glitches
+    assert!(find_glitches(&sine(1000.0, 100e3, 1500), 2, 0.0).is_empty());
+    assert_eq!(find_glitches(&points, 2, 4.0).len(), 0);
+}
+
+#[test]
+fn times_the_settling_of_a_damped_step() {
+    // A step to 1 V at 3 ms ringing at 5 kHz and decaying with a time constant of 100 us
+    let step: Vec<Point> = (0..1500)
+        .map(|index| {
+            let time = index as f64 / 100e3;
+            let since = time - 3e-3;
+            let voltage = if since < 0.0 { 0.0 } else { 1.0 - (-since / 1e-4).exp() * (2.0 * std::f64::consts::PI * 5e3 * since).cos() };
+            Point { time: time as f32, voltage: voltage as f32 }
+        })
+        .collect();
+    let settling_s = settling_time(&step, 2.0).unwrap();
+
+    // The envelope is within 2% of the step after 100 us * ln(50) = 391 us, the ringing crosses back inside it earlier
+    assert!(settling_s > 250e-6 && settling_s <= 391e-6, "{} s", settling_s);
+    assert!(settling_time(&step, 10.0).unwrap() < settling_s);
+    assert_eq!(settling_time(&sine(1000.0, 100e3, 1500), 2.0), None);
+}
+
+#[test]
+fn classifies_the_coupling_of_the_signals() {
+    let sine = sine(1000.0, 100e3, 1500);
+    let shifted = |offset_v: f32| sine.iter().map(|point| Point { time: point.time, voltage: point.voltage + offset_v }).collect::<Vec<_>>();
+
+    assert_eq!(classify_signal(&sine), SignalClass::Ac);
+    assert_eq!(serde_json::to_value(classify_signal(&sine)).unwrap(), "ac");
+    assert_eq!(classify_signal(&shifted(1.0)), SignalClass::Mixed);
+    assert_eq!(classify_signal(&shifted(10.0)), SignalClass::Dc);
+    assert_eq!(classify_signal(&shifted(-10.0)), SignalClass::Dc);
+    assert_eq!(classify_signal(&[]), SignalClass::Dc);
+}
+
+#[test]
+fn averages_the_folded_periods() {
+    // Uniform noise of up to 0.5 V from a linear congruential generator on a 1 kHz sine, 15 periods of 100 samples
+    let mut state = 12345u32;
+    let clean = sine(1000.0, 100e3, 1500);
+    let noisy: Vec<Point> = clean.iter()
+        .map(|point| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            Point { time: point.time, voltage: point.voltage + (state >> 16) as f32 / 65536.0 - 0.5 }
+        })
+        .collect();
+    let rms_error = |voltages: &[f32]| (voltages.iter().zip(&clean).map(|(voltage, point)| (voltage - point.voltage).powi(2)).sum::<f32>() / voltages.len() as f32).sqrt();
+    let periods = fold_waveform(&noisy, 100);
+    let average = phase_fold_average(&noisy, 100);
+
+    assert_eq!(periods.len(), 15);
+    assert!(periods.iter().all(|period| period.len() == 100));
+    assert_eq!(periods[3][..], noisy[300..400].iter().map(|point| point.voltage).collect::<Vec<_>>()[..]);
+    assert_eq!(average.len(), 100);
+    // The noise of 0.29 V RMS shrinks by the square root of the 15 periods to 0.075 V
+    assert!(rms_error(&periods[0]) > 0.2, "{}", rms_error(&periods[0]));
+    assert!(rms_error(&average) < 0.12, "{}", rms_error(&average));
+    assert_eq!(fold_waveform(&noisy, 1000).len(), 1);
+    assert!(phase_fold_average(&noisy, 2000).is_empty());
+    assert!(fold_waveform(&noisy, 0).is_empty());
+}
+
+#[test]
+fn computes_the_crest_factor() {
+    let square: Vec<Point> = (0..1500).map(|index| Point { time: index as f32 * 1e-5, voltage: if index / 50 % 2 == 0 { 2.0 } else { -2.0 } }).collect();
+    let flat: Vec<Point> = (0..1500).map(|index| Point { time: index as f32 * 1e-5, voltage: 0.0 }).collect();
+
+    assert!((crest_factor(&sine(1000.0, 100e3, 1500)).unwrap() - std::f32::consts::SQRT_2).abs() < 1e-3);
+    assert!((crest_factor(&square).unwrap() - 1.0).abs() < 1e-6);
+    assert_eq!(crest_factor(&flat), None);
+    assert_eq!(crest_factor(&[]), None);
+}
+
+#[test]
+fn oversampling_averages_out_the_noise() {
+    // Uniform noise of up to 0.5 V around 1 V from a linear congruential generator
+    let mut state = 12345u32;
+    let noisy: Vec<Point> = (0..1500)
+        .map(|index| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            Point { time: index as f32 * 1e-5, voltage: 1.0 + (state >> 16) as f32 / 65536.0 - 0.5 }
+        })
+        .collect();
+    let deviation = |points: &[Point]| {
+        let mean = points.iter().map(|point| point.voltage).sum::<f32>() / points.len() as f32;
+        (points.iter().map(|point| (point.voltage - mean).powi(2)).sum::<f32>() / points.len() as f32).sqrt()
+    };
+    let averaged = oversample_average(&noisy, 4);
+
+    assert_eq!(averaged.len(), 375);
+    assert_eq!(averaged[1].time, noisy[6].time);
+    // Averaging 4 samples halves the deviation of the 0.29 V of the noise
+    assert!((deviation(&averaged) / deviation(&noisy) - 0.5).abs() < 0.1, "{} {}", deviation(&averaged), deviation(&noisy));
+    assert_eq!(oversample_average(&noisy, 1).len(), 1500);
+}
+
+#[test]
+fn estimates_the_period_from_the_fft() {
+    let period_s = period_estimation_fft(&sine(1000.0, 100e3, 1500), 100e3).unwrap();
+
+    assert!((period_s * 1e6 - 1000.0).abs() <= 0.1, "{} us", period_s * 1e6);
+}
+
+#[test]
+fn finds_no_period_without_a_fundamental() {
+    let flat: Vec<Point> = (0..1500).map(|index| Point { time: index as f32 * 1e-5, voltage: 1.5 }).collect();
+
+    assert_eq!(period_estimation_fft(&flat, 100e3), None);
+}
+
+#[test]
+fn computes_the_offset_centering_the_samples() {
+    assert_eq!(compute_optimal_offset(&[400, 500, 300, 410, 390]), 400);
+    assert_eq!(compute_optimal_offset(&[]), ADC_MIDPOINT);
+}
+
+#[test]
+fn validates_the_frequency_against_the_window() {
+    // 50 kHz sampling a 30 ms window
+    let one_milli = Scale::<Second>::try_from(14).unwrap();
+
+    assert_eq!(validate_time_scale_vs_frequency(&one_milli, 1000), ValidationResult::Ok);
+    assert_eq!(validate_time_scale_vs_frequency(&one_milli, 0), ValidationResult::Ok);
+    assert!(matches!(
+        validate_time_scale_vs_frequency(&one_milli, 30_000),
+        ValidationResult::TooHighForWindow { max_measurable, claimed } if (max_measurable - 25_000.0).abs() < 1e-2 && claimed == 30_000.0
+    ));
+    assert!(matches!(
+        validate_time_scale_vs_frequency(&one_milli, 50),
+        ValidationResult::TooLowForWindow { min_measurable, claimed } if (min_measurable - 66.667).abs() < 1e-3 && claimed == 50.0
+    ));
+}
+
+#[test]
+fn interpolates_between_the_points() {
+    let points = sine(1000.0, 100e3, 100);
+    let linear = interpolate(&points, InterpolationMethod::Linear, 2);
+
+    assert_eq!(linear.len(), 199);
+    assert_eq!((linear[40].time, linear[40].voltage), (points[20].time, points[20].voltage));
+    assert!((linear[41].voltage - (points[20].voltage + points[21].voltage) / 2.0).abs() < 1e-6);
+    assert!((linear[41].time - (points[20].time + points[21].time) / 2.0).abs() < 1e-9);
+
+    // A cubic through a sampled sine stays closer to it than the chords between the points
+    let cubic = interpolate(&points, InterpolationMethod::Cubic, 4);
+    let between = 2.0 * std::f32::consts::PI * 1000.0 * cubic[81].time;
+
+    assert_eq!(cubic.len(), 397);
+    assert!((cubic[81].voltage - between.sin()).abs() < 1e-3);
+}
+
+#[test]
+fn computes_the_resolution_of_the_capture() {
+    let ten_millis = Scale { value: 10.0, scale: -3, unit: Volt };
+    // 50 kHz sampling
+    let one_milli = Scale::<Second>::try_from(14).unwrap();
+    let uncertainty = compute_uncertainty(&ten_millis, &one_milli, 1500);
+
+    // 50 counts per division
+    assert!((uncertainty.voltage_resolution_mv - 0.2).abs() < 1e-6);
+    assert!((uncertainty.frequency_resolution_hz - 33.333).abs() < 1e-3);
+    assert!((uncertainty.time_resolution_ns - 20_000.0).abs() < 1e-3);
+}
+
+#[test]
+fn keeps_the_peaks_when_downsampling() {
+    let mut points: Vec<Point> = (0..1500).map(|index| Point { time: index as f32 * 1e-5, voltage: 0.5 }).collect();
+    points[777].voltage = 3.0;
+    let downsampled = downsample_minmax(&points, 150);
+
+    assert_eq!(downsampled.len(), 150);
+    // Decimating every tenth point would skip the spike at 777
+    assert_eq!((downsampled[77].min_v, downsampled[77].max_v), (0.5, 3.0));
+    assert!(downsampled.iter().enumerate().filter(|(index, _)| *index != 77).all(|(_, point)| point.max_v == 0.5));
+    assert!((downsampled[77].time_s - 770e-5).abs() < 1e-9);
+    assert_eq!(downsample_minmax(&points[..20], 150).len(), 20);
+}
+
+#[test]
+fn resamples_to_a_common_rate() {
+    let points = sine(1000.0, 100e3, 1500);
+    let downsampled = resample(&points, 50e3, 100e3);
+    let upsampled = resample(&points, 200e3, 100e3);
+
+    assert_eq!(downsampled.len(), 750);
+    assert!((downsampled[1].time - 20e-6).abs() < 1e-9);
+    assert!((1.0 / period_estimation_fft(&downsampled, 50e3).unwrap() - 1000.0).abs() < 0.1);
+    // Every other point of the upsampled sine is one of the original points, the others are on the sine between them
+    assert_eq!(upsampled.len(), 3000);
+    assert!(upsampled.iter().step_by(2).zip(&points).all(|(resampled, point)| (resampled.voltage - point.voltage).abs() < 1e-4));
+    assert!((upsampled[1].voltage - (2.0 * std::f32::consts::PI * 1000.0 * 5e-6).sin()).abs() < 1e-4);
+}
+
+#[test]
+fn compares_the_frequency_measured_three_ways() {
+    let points = sine(1000.0, 100e3, 1500);
+    let comparison = compare_frequencies(1000, &points, 100e3, MEASUREMENT_TOLERANCE);
+
+    for frequency_hz in [comparison.device_hz, comparison.zero_crossing_hz, comparison.fft_hz] {
+        assert!((frequency_hz.unwrap() - 1000.0).abs() < 1.0, "{:?}", comparison);
+    }
+    assert!(comparison.agree);
+    assert!(!compare_frequencies(1200, &points, 100e3, MEASUREMENT_TOLERANCE).agree);
+    assert!(!compare_frequencies(0, &points, 100e3, MEASUREMENT_TOLERANCE).agree);
+}
+
+#[test]
+fn classifies_the_waveforms() {
+    let wave = |shape: fn(f64) -> f64| (0..1500)
+        .map(|index| {
+            let time = index as f64 / 100e3;
+            Point { time: time as f32, voltage: shape((time * 1000.0).fract()) as f32 }
+        })
+        .collect::<Vec<_>>();
+    // Uniform noise from a linear congruential generator
+    let mut state = 12345u32;
+    let noise: Vec<Point> = (0..1500)
+        .map(|index| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            Point { time: index as f32 * 1e-5, voltage: (state >> 16) as f32 / 32768.0 - 1.0 }
+        })
+        .collect();
+
+    assert_eq!(detect_signal_type(&sine(1000.0, 100e3, 1500), 100e3), SignalType::Sine);
+    assert_eq!(detect_signal_type(&wave(|phase| if phase < 0.5 { 1.0 } else { -1.0 }), 100e3), SignalType::Square);
+    assert_eq!(detect_signal_type(&wave(|phase| 1.0 - 4.0 * (phase - 0.5).abs()), 100e3), SignalType::Triangle);
+    assert_eq!(detect_signal_type(&wave(|phase| 2.0 * phase - 1.0), 100e3), SignalType::Sawtooth);
+    assert_eq!(detect_signal_type(&wave(|phase| 1.0 - 2.0 * phase), 100e3), SignalType::Sawtooth);
+    assert_eq!(detect_signal_type(&noise, 100e3), SignalType::Noise);
+    assert_eq!(detect_signal_type(&wave(|_| 1.5), 100e3), SignalType::Dc);
+    // A 2 V level with a little ripple is still DC
+    assert_eq!(detect_signal_type(&wave(|phase| 2.0 + 0.05 * (2.0 * std::f64::consts::PI * phase).sin()), 100e3), SignalType::Dc);
+
+    let thd = total_harmonic_distortion(&wave(|phase| if phase < 0.5 { 1.0 } else { -1.0 }), 1000.0, 100e3).unwrap();
+    assert!((thd - 0.483).abs() < 0.02, "{}", thd);
+}
+
+#[test]
+fn harmonic_distortion_needs_a_period_in_the_points() {
+    let sine = sine(1000.0, 100e3, 1500);
+
+    assert!(total_harmonic_distortion(&sine, 1000.0, 100e3).unwrap() < 0.01);
+    // 1500 points at 100 kHz hold a single period of 66.7 Hz
+    assert!(total_harmonic_distortion(&sine, 66.7, 100e3).is_some());
+    assert_eq!(total_harmonic_distortion(&sine, 1e-9, 100e3), None);
+    assert_eq!(total_harmonic_distortion(&sine, 0.0, 100e3), None);
+    assert_eq!(total_harmonic_distortion(&sine, f64::NAN, 100e3), None);
+    assert_eq!(total_harmonic_distortion(&sine, 1000.0, f64::NAN), None);
+    assert_eq!(total_harmonic_distortion(&sine, f64::INFINITY, 100e3), None);
+    assert_eq!(total_harmonic_distortion(&sine, 1000.0, 0.0), None);
+}
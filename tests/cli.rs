@@ -0,0 +1,655 @@
+//! Runs the cli on the captures under `tests/fixtures`, see `tests/parse.rs` for what they hold
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use serde_json::Value;
+
+fn fnirsi(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fnirsi"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+fn json(args: &[&str]) -> Value {
+    let output = fnirsi(args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// Writes `sine_1khz.bin` changed by `edit` to a capture of its own in the temporary directory
+fn edited_capture(name: &str, edit: impl FnOnce(&mut Vec<u8>)) -> PathBuf {
+    let mut bytes = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/sine_1khz.bin")).unwrap();
+    edit(&mut bytes);
+
+    let path = std::env::temp_dir().join(format!("fnirsi-{}.bin", name));
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[test]
+fn estimates_a_missing_device_frequency() {
+    let path = edited_capture("missing-frequency", |bytes| {
+        // Both halves of the channel 1 frequency
+        bytes[232..236].copy_from_slice(&[0; 4]);
+    });
+    let parsed = json(&["parsed", path.to_str().unwrap()]);
+    let measurements = &parsed["channel1"]["measurements"];
+    let frequency_hz = measurements["frequency_estimated_hz"].as_f64().unwrap();
+
+    assert_eq!(measurements["frequency"], 0);
+    // Within 5% of the sine
+    assert!((frequency_hz - 1000.0).abs() <= 50.0, "{} Hz", frequency_hz);
+    // The 500 Hz of channel 2 is plausible at 100us/div
+    assert!(parsed["channel2"]["measurements"]["frequency_estimated_hz"].is_null());
+    // Not even the period of the sine itself correlates beyond 1
+    let parsed = json(&["parsed", "--min-autocorrelation", "1.1", path.to_str().unwrap()]);
+    assert!(parsed["channel1"]["measurements"]["frequency_estimated_hz"].is_null());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn channels_short_of_min_samples_are_rejected() {
+    // Both channels of the capture hold 1500 samples
+    let output = fnirsi(&["parsed", "--min-samples", "1501", "tests/fixtures/sine_1khz.bin"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("Channel 1 has 1500 samples but at least 1501 are required"), "{}", stderr);
+    assert_eq!(json(&["parsed", "--min-samples", "1500", "tests/fixtures/sine_1khz.bin"])["channel1"]["points"].as_array().unwrap().len(), 1500);
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet_output_holds_the_points() {
+    use arrow::array::Float64Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let path = std::env::temp_dir().join("fnirsi-parquet-output.parquet");
+    let output = fnirsi(&["parquet", "--output-file", path.to_str().unwrap(), "tests/fixtures/sine_1khz.bin"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let parsed = json(&["parsed", "tests/fixtures/sine_1khz.bin"]);
+    let builder = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(builder.metadata().file_metadata().num_rows(), 1500);
+    assert_eq!(builder.schema().metadata()["channel1_scale"], "1V");
+    let batches = builder.build().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    let column = |name: &str| batches[0].column_by_name(name).unwrap().as_any().downcast_ref::<Float64Array>().unwrap().clone();
+
+    assert_eq!(batches.iter().map(|batch| batch.num_rows()).sum::<usize>(), 1500);
+    // The columns widen the f32 points the json holds the shortest decimal of
+    assert_eq!(column("ch1_v").value(0) as f32, parsed["channel1"]["points"][0]["voltage"].as_f64().unwrap() as f32);
+    assert_eq!(column("time_s").value(1) as f32, parsed["channel1"]["points"][1]["time"].as_f64().unwrap() as f32);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn triggers_at_the_midpoint_of_the_trigger_channel() {
+    let path = edited_capture("trigger-50", |bytes| {
+        // A square 1 and 5 divisions above the offset 400 of channel 1 at 1V/div
+        for index in 0..1500 {
+            let sample: u16 = if index / 100 % 2 == 0 { 450 } else { 650 };
+            bytes[1000 + 2 * index..1002 + 2 * index].copy_from_slice(&sample.to_le_bytes());
+        }
+        // 50% trigger on channel 1
+        bytes[124..126].copy_from_slice(&0u16.to_le_bytes());
+        bytes[30..32].copy_from_slice(&0u16.to_le_bytes());
+    });
+    let parsed = json(&["parsed", path.to_str().unwrap()]);
+
+    assert_eq!(parsed["trigger"]["trigger_50"], "On");
+    assert!((parsed["trigger"]["level_v"].as_f64().unwrap() - 3.0).abs() < 1e-4, "{}", parsed["trigger"]);
+    // The 50% trigger of the fixture is off
+    assert!(json(&["parsed", "tests/fixtures/sine_1khz.bin"])["trigger"]["level_v"].is_null());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn json_root_wraps_the_output() {
+    let wrapped = json(&["parsed", "--json-root", "capture", "tests/fixtures/sine_1khz.bin"]);
+
+    assert_eq!(wrapped.as_object().unwrap().keys().collect::<Vec<_>>(), ["capture"]);
+    assert_eq!(wrapped["capture"], json(&["parsed", "tests/fixtures/sine_1khz.bin"]));
+    assert_eq!(json(&["raw", "--json-root", "capture", "tests/fixtures/sine_1khz.bin"])["capture"], json(&["raw", "tests/fixtures/sine_1khz.bin"]));
+}
+
+#[test]
+fn flat_samples_on_a_midpoint_offset_are_0v() {
+    let path = edited_capture("midpoint-offset", |bytes| {
+        // Channel 1 centered on the screen and flat on its offset
+        bytes[84..86].copy_from_slice(&512u16.to_le_bytes());
+        for index in 0..1500 {
+            bytes[1000 + 2 * index..1002 + 2 * index].copy_from_slice(&512u16.to_le_bytes());
+        }
+    });
+    let parsed = json(&["parsed", path.to_str().unwrap()]);
+    let points = parsed["channel1"]["points"].as_array().unwrap();
+
+    assert_eq!(points.len(), 1500);
+    assert!(points.iter().all(|point| point["voltage"].as_f64().unwrap().abs() < 1e-6));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn human_measurements_pick_the_si_prefix() {
+    let path = edited_capture("human-measurements", |bytes| {
+        // A vpp of 256 / 1024 V on channel 1
+        bytes[226..228].copy_from_slice(&256u16.to_le_bytes());
+    });
+    let parsed = json(&["parsed", "--human-measurements", path.to_str().unwrap()]);
+    let measurements = &parsed["channel1"]["measurements"];
+
+    assert_eq!(measurements["vpp"], 0.25);
+    assert_eq!(measurements["vpp_display"], "250 mV");
+    assert_eq!(measurements["vmax_display"], "2 V");
+    assert_eq!(measurements["vrms_display"], "1.414 V");
+    assert!(json(&["parsed", path.to_str().unwrap()])["channel1"]["measurements"].get("vpp_display").is_none());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn scores_the_quality_of_the_channels() {
+    let channel1 = |name: &str, vpp: u16, sample: &dyn Fn(usize) -> u16| edited_capture(name, |bytes| {
+        // Channel 1 centered on the screen
+        bytes[84..86].copy_from_slice(&512u16.to_le_bytes());
+        bytes[226..228].copy_from_slice(&vpp.to_le_bytes());
+        for index in 0..1500 {
+            bytes[1000 + 2 * index..1002 + 2 * index].copy_from_slice(&sample(index).to_le_bytes());
+        }
+    });
+    // A sine of 300 counts saturating at the edges of the screen 200 counts from the offset
+    let clipped = channel1("clipped", 4096, &|index| {
+        let sample = 512.0 + 300.0 * (2.0 * std::f32::consts::PI * index as f32 / 500.0).sin();
+        sample.round().clamp(312.0, 712.0) as u16
+    });
+    // Samples jumping 100 counts around the offset at random, from a linear congruential generator,
+    // with the 1.98 V of the 99 counts between their extremes at 1V/div as the device vpp
+    let noise = std::cell::Cell::new(12345u32);
+    let noisy = channel1("noisy", 2028, &|_| {
+        noise.set(noise.get().wrapping_mul(1_103_515_245).wrapping_add(12345));
+        462 + (noise.get() >> 16) as u16 % 100
+    });
+    let quality = |path: &str| json(&["parsed", path]);
+
+    let clean = quality("tests/fixtures/sine_1khz.bin");
+    assert!(clean["channel1"]["quality"]["score"].as_f64().unwrap() > 0.95, "{}", clean["channel1"]["quality"]);
+    assert_eq!(clean["channel1"]["quality"]["clipping_fraction"], 0.0);
+
+    let clipped_quality = quality(clipped.to_str().unwrap());
+    assert!(clipped_quality["channel1"]["quality"]["score"].as_f64().unwrap() < 0.5, "{}", clipped_quality["channel1"]["quality"]);
+    assert!(clipped_quality["channel1"]["quality"]["clipping_fraction"].as_f64().unwrap() > 0.5);
+
+    // The device vpp agrees with the points, the noise alone spoils the channel
+    let noisy_quality = quality(noisy.to_str().unwrap());
+    assert!(noisy_quality["channel1"]["quality"]["vpp_mismatch"].as_f64().unwrap() < 0.01, "{}", noisy_quality["channel1"]["quality"]);
+    assert!(noisy_quality["channel1"]["quality"]["score"].as_f64().unwrap() < 0.1, "{}", noisy_quality["channel1"]["quality"]);
+    assert_eq!(noisy_quality["quality_score"], noisy_quality["channel1"]["quality"]["score"]);
+
+    std::fs::remove_file(clipped).unwrap();
+    std::fs::remove_file(noisy).unwrap();
+}
+
+#[test]
+fn numbers_ignore_the_locale() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fnirsi"))
+        .args(["parsed", "--human-measurements", "tests/fixtures/sine_1khz.bin"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("LANG", "de_DE.UTF-8")
+        .env("LC_NUMERIC", "de_DE.UTF-8")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let parsed: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(parsed["channel1"]["measurements"]["vrms_display"], "1.414 V");
+    assert_eq!(output.stdout, fnirsi(&["parsed", "--human-measurements", "tests/fixtures/sine_1khz.bin"]).stdout);
+}
+
+#[test]
+fn every_file_gets_its_sequence_index() {
+    let sequence = |args: &[&str]| {
+        let output = fnirsi(args);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+        output.stdout.split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<Value>(line).unwrap()["sequence_index"].clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(sequence(&["parsed", "tests/fixtures/sine_1khz.bin", "tests/fixtures/sine_1khz.bin"]), [0, 1]);
+    assert_eq!(sequence(&["parsed", "--repeat", "5", "tests/fixtures/sine_1khz.bin", "tests/fixtures/sine_1khz.bin"]), [5, 6]);
+    assert!(json(&["parsed", "tests/fixtures/sine_1khz.bin"]).get("sequence_index").is_none());
+}
+
+#[test]
+fn base64_samples_decode_to_the_raw_ones() {
+    use base64::Engine;
+
+    let raw = json(&["raw", "tests/fixtures/sine_1khz.bin"]);
+    let encoded = json(&["raw", "--raw-samples-encoding", "base64", "tests/fixtures/sine_1khz.bin"]);
+
+    assert_eq!(encoded["header"], raw["header"]);
+    for buffer in ["channel11", "channel21", "channel12", "channel22"] {
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded[buffer].as_str().unwrap()).unwrap();
+        let samples = bytes.chunks(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect::<Vec<_>>();
+        let expected = raw[buffer].as_array().unwrap().iter().map(|sample| sample.as_u64().unwrap() as u16).collect::<Vec<_>>();
+
+        assert_eq!(samples, expected, "{}", buffer);
+    }
+}
+
+#[test]
+fn skip_bom_parses_a_mangled_capture() {
+    let path = edited_capture("bom-prefixed", |bytes| {
+        bytes.splice(0..0, [0xEF, 0xBB, 0xBF]);
+    });
+    let parsed = json(&["parsed", "--skip-bom", path.to_str().unwrap()]);
+
+    assert_eq!(parsed, json(&["parsed", "tests/fixtures/sine_1khz.bin"]));
+    // Every field of the capture is 3 bytes off without it
+    assert!(!fnirsi(&["parsed", path.to_str().unwrap()]).status.success());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn zoom_buffer_builds_the_points_from_its_own_samples() {
+    let path = edited_capture("zoom-buffer", |bytes| {
+        // The first sample of the zoom buffer of channel 1, a division above its offset of 400
+        bytes[7000..7002].copy_from_slice(&450u16.to_le_bytes());
+    });
+    let raw = json(&["raw", path.to_str().unwrap()]);
+    let zoom = json(&["parsed", "--buffer", "zoom", path.to_str().unwrap()]);
+    let points = zoom["channel1"]["points"].as_array().unwrap();
+
+    assert_eq!(points.len(), 750);
+    assert_eq!(zoom["channel2"]["points"].as_array().unwrap().len(), 750);
+    assert_eq!(points[0]["voltage"], 1.0);
+    assert_eq!(json(&["parsed", path.to_str().unwrap()])["channel1"]["points"][0]["voltage"], 0.0);
+    for (point, sample) in points.iter().zip(raw["channel12"].as_array().unwrap()) {
+        let voltage = (sample.as_f64().unwrap() - 400.0) / 50.0;
+        assert!((point["voltage"].as_f64().unwrap() - voltage).abs() < 1e-6, "{} {}", point, sample);
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn closed_pipes_end_the_output_quietly() {
+    use std::process::Stdio;
+
+    // Several times the size of a pipe buffer, the cli is still writing when the reader goes away
+    let mut child = Command::new(env!("CARGO_BIN_EXE_fnirsi"))
+        .args(["parsed", "tests/fixtures/sine_1khz.bin", "tests/fixtures/sine_1khz.bin", "tests/fixtures/sine_1khz.bin", "tests/fixtures/sine_1khz.bin"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.is_empty(), "{}", stderr);
+}
+
+#[test]
+fn reverse_samples_flips_the_waveform() {
+    let forward = json(&["parsed", "tests/fixtures/sine_1khz.bin"]);
+    let reversed = json(&["parsed", "--reverse-samples", "tests/fixtures/sine_1khz.bin"]);
+    let (forward, reversed) = (forward["channel1"]["points"].as_array().unwrap(), reversed["channel1"]["points"].as_array().unwrap());
+    let time = |point: &Value| point["time"].as_f64().unwrap();
+
+    assert_eq!(reversed.len(), 1500);
+    for (index, point) in reversed.iter().enumerate() {
+        assert_eq!(point["voltage"], forward[1499 - index]["voltage"], "{}", index);
+        assert_eq!(point["time"], forward[index]["time"], "{}", index);
+    }
+    assert!(reversed.windows(2).all(|pair| time(&pair[0]) < time(&pair[1])));
+}
+
+#[test]
+fn measurements_csv_has_a_row_per_capture() {
+    let path = edited_capture("measurements-csv", |bytes| {
+        // Channel 1 at 200mV/div
+        bytes[4..6].copy_from_slice(&4u16.to_le_bytes());
+    });
+    let output = fnirsi(&["measurements-csv", "tests/fixtures/sine_1khz.bin", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let csv = String::from_utf8(output.stdout).unwrap();
+    let rows = csv.lines().map(|row| row.split(',').collect::<Vec<_>>()).collect::<Vec<_>>();
+    let value = |row: usize, column: &str| rows[row][rows[0].iter().position(|name| *name == column).unwrap()];
+
+    // The filename, the 4 trigger columns and 15 columns for every channel
+    assert_eq!(rows.len(), 3);
+    assert!(rows.iter().all(|row| row.len() == 35), "{}", csv);
+    assert_eq!(rows[0][..5], ["filename", "trigger_type", "trigger_edge", "trigger_channel", "trigger_50"]);
+    assert_eq!(value(1, "filename"), "tests/fixtures/sine_1khz.bin");
+    assert_eq!(value(1, "ch1_vpp"), "4");
+    assert_eq!(value(1, "ch1_frequency"), "1000");
+    assert_eq!(value(1, "ch1_frequency_estimated_hz"), "");
+    assert_eq!(value(1, "ch2_coupling"), "AC");
+    assert_eq!(value(1, "ch2_scale_v"), "5");
+    assert_eq!(value(1, "ch2_attenuation"), "10");
+    assert_eq!(value(2, "ch1_scale_v"), "0.2");
+    assert_eq!(value(2, "filename"), path.to_str().unwrap());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn time_origin_shifts_the_times() {
+    let shifted = json(&["parsed", "--time-origin", "10.0", "tests/fixtures/sine_1khz.bin"]);
+    let points = shifted["channel1"]["points"].as_array().unwrap();
+
+    assert_eq!(points[0]["time"], 10.0);
+    assert_eq!(shifted["channel2"]["points"][0]["time"], 10.0);
+    // 2 us later, as close as an f32 around 10 gets
+    assert!((points[1]["time"].as_f64().unwrap() - 10.000002).abs() < 1e-6, "{}", points[1]);
+    assert_eq!(points[0]["voltage"], json(&["parsed", "tests/fixtures/sine_1khz.bin"])["channel1"]["points"][0]["voltage"]);
+}
+
+#[test]
+fn diagnostics_report_what_was_read() {
+    let diagnostics = &json(&["parsed", "--diagnostics", "tests/fixtures/sine_1khz.bin"])["diagnostics"];
+    let file_size = std::fs::metadata("tests/fixtures/sine_1khz.bin").unwrap().len();
+
+    assert_eq!(diagnostics["channel1_samples"], 1500);
+    assert_eq!(diagnostics["channel2_samples"], 1500);
+    assert_eq!(diagnostics["channel1_zoom_samples"], 750);
+    assert_eq!(diagnostics["file_size"], file_size);
+    assert_eq!(diagnostics["bytes_read"], file_size);
+    assert_eq!(diagnostics["fallbacks"].as_array().unwrap().len(), 0);
+    assert!(json(&["parsed", "tests/fixtures/sine_1khz.bin"]).get("diagnostics").is_none());
+}
+
+#[test]
+fn ascii_lines_fit_in_the_width() {
+    for width in ["10", "40", "120"] {
+        let output = fnirsi(&["ascii", "--width", width, "tests/fixtures/sine_1khz.bin"]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let ascii = String::from_utf8(output.stdout).unwrap();
+        let widest = ascii.lines().map(|line| line.chars().count()).max().unwrap();
+
+        assert!(widest <= width.parse().unwrap(), "{} characters at --width {}:\n{}", widest, width, ascii);
+    }
+    // The plot of the sine reaches across the whole width
+    let ascii = String::from_utf8(fnirsi(&["ascii", "--width", "40", "tests/fixtures/sine_1khz.bin"]).stdout).unwrap();
+    assert!(ascii.lines().any(|line| line.chars().count() == 40));
+    assert!(ascii.starts_with("CH1 1V/div  T 100us/div\n"));
+    assert!(String::from_utf8(fnirsi(&["ascii", "--width", "10", "tests/fixtures/sine_1khz.bin"]).stdout).unwrap().starts_with("CH1 1V/div\n"));
+}
+
+#[test]
+fn frequency_word_order_swaps_the_words() {
+    let path = edited_capture("swapped-frequency", |bytes| {
+        // The 1 kHz of channel 1 with its words swapped, the high word at 232 holding the low one
+        bytes[232..234].copy_from_slice(&1000u16.to_le_bytes());
+        bytes[234..236].copy_from_slice(&0u16.to_le_bytes());
+    });
+
+    let swapped = json(&["parsed", "--frequency-word-order", "lh", path.to_str().unwrap()]);
+    let default = json(&["parsed", path.to_str().unwrap()]);
+
+    assert_eq!(swapped["channel1"]["measurements"]["frequency"], 1000);
+    assert!(swapped["channel1"]["measurements"].get("frequency_estimated_hz").is_none());
+    assert_eq!(default["channel1"]["measurements"]["frequency"], 1000u64 << 16);
+    // The unswapped words are far beyond the nyquist limit, so the frequency is estimated from the samples instead
+    assert!((default["channel1"]["measurements"]["frequency_estimated_hz"].as_f64().unwrap() - 1000.0).abs() < 50.0);
+    // Channel 2 keeps the 500 Hz in its low word, which reads as 500 << 16 swapped
+    assert_eq!(swapped["channel2"]["measurements"]["frequency"], 500u64 << 16);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn raw_output_holds_the_header_and_sample_buffers() {
+    let raw = json(&["raw", "tests/fixtures/sine_1khz.bin"]);
+    let mut keys = raw.as_object().unwrap().keys().collect::<Vec<_>>();
+    keys.sort();
+
+    assert_eq!(keys, ["channel11", "channel12", "channel21", "channel22", "header"]);
+    assert_eq!(raw["channel11"].as_array().unwrap().len(), 1500);
+    assert_eq!(raw["channel21"].as_array().unwrap().len(), 1500);
+    assert_eq!(raw["channel12"].as_array().unwrap().len(), 750);
+    assert_eq!(raw["channel22"].as_array().unwrap().len(), 750);
+    assert_eq!(raw["header"]["time_scale"], 17);
+    assert_eq!(raw["header"]["channel2_probe"], 1);
+    assert_eq!(raw["header"]["channel1_measurements"]["vpp"], 4096);
+    assert_eq!(raw["header"]["channel2_measurements"]["frequency_low"], 500);
+}
+
+#[test]
+fn parsed_output_holds_the_scaled_channels() {
+    let parsed = json(&["parsed", "tests/fixtures/sine_1khz.bin"]);
+
+    assert_eq!(parsed["time_scale"]["value"], 100.0);
+    assert_eq!(parsed["time_scale"]["scale"], -6);
+    assert_eq!(parsed["channel1"]["coupling"], "DC");
+    assert_eq!(parsed["channel2"]["attenuation"], "TenX");
+    assert_eq!(parsed["channel1"]["measurements"]["frequency"], 1000);
+    assert_eq!(parsed["channel2"]["measurements"]["frequency"], 500);
+    assert_eq!(parsed["channel1"]["points"].as_array().unwrap().len(), 1500);
+    assert_eq!(parsed["channel2"]["points"][0]["voltage"], 5.0);
+}
+
+#[test]
+fn fft_adds_the_estimated_period() {
+    let parsed = json(&["parsed", "--fft", "tests/fixtures/sine_1khz.bin"]);
+    let period_ns = parsed["channel1"]["measurements"]["estimated_period_ns"].as_f64().unwrap();
+
+    assert!((period_ns - 1e6).abs() < 100.0, "{}", period_ns);
+    assert!(json(&["parsed", "tests/fixtures/sine_1khz.bin"])["channel1"]["measurements"].get("estimated_period_ns").is_none());
+}
+
+#[test]
+fn validate_compares_the_offsets() {
+    let validation = json(&["parsed", "--validate", "tests/fixtures/dc_levels.bin"]);
+
+    assert_eq!(validation["channel1"]["stored_offset"], 400);
+    assert_eq!(validation["channel1"]["computed_offset"], 450);
+    assert_eq!(validation["channel2"]["stored_offset"], 512);
+    assert_eq!(validation["channel2"]["computed_offset"], 487);
+    assert_eq!(validation["channel1"]["frequency"]["result"], "ok");
+
+    // 4 mV per count at 200mV/div and 20 us between the samples at 1ms/div
+    assert_eq!(validation["channel1"]["uncertainty"]["voltage_resolution_mv"], 4.0);
+    assert_eq!(validation["channel1"]["uncertainty"]["time_resolution_ns"], 20_000.0);
+
+    // Only one and a half periods of the 500 Hz square fit the window
+    let validation = json(&["parsed", "--validate", "tests/fixtures/sine_1khz.bin"]);
+
+    assert_eq!(validation["channel1"]["frequency"]["result"], "ok");
+    assert_eq!(validation["channel2"]["frequency"]["result"], "too_low_for_window");
+    assert_eq!(validation["channel2"]["frequency"]["claimed"], 500.0);
+}
+
+#[test]
+fn dataframe_output_follows_the_orient() {
+    let records = json(&["dataframe", "tests/fixtures/sine_1khz.bin"]);
+    let split = json(&["dataframe", "--json-orient", "split", "tests/fixtures/sine_1khz.bin"]);
+    let columns = json(&["dataframe", "--json-orient", "columns", "tests/fixtures/sine_1khz.bin"]);
+
+    assert_eq!(records["channel1"].as_array().unwrap().len(), 1500);
+    assert_eq!(records["channel1"][1], serde_json::json!({ "time_s": 2e-6, "voltage_v": 0.02 }));
+    assert_eq!(split["channel2"]["voltage_v"].as_array().unwrap().len(), 1500);
+    assert_eq!(split["channel1"]["time_s"][1], 2e-6);
+    assert_eq!(columns["channel1"]["voltage_v"]["1"], 0.02);
+    assert_eq!(columns["channel2"]["time_s"].as_object().unwrap().keys().nth(10).unwrap(), "10");
+}
+
+#[test]
+fn split_buffers_lists_every_buffer_with_its_offset() {
+    let buffers = json(&["raw", "--split-buffers", "tests/fixtures/sine_1khz.bin"]);
+    let buffers: Vec<_> = buffers.as_array().unwrap().iter()
+        .map(|buffer| (buffer["name"].as_str().unwrap(), buffer["offset"].as_u64().unwrap(), buffer["samples"].as_array().unwrap().len()))
+        .collect();
+
+    assert_eq!(buffers, [("channel11", 1000, 1500), ("channel21", 4000, 1500), ("channel12", 7000, 750), ("channel22", 8500, 750)]);
+}
+
+#[test]
+fn dry_run_checks_every_capture() {
+    let output = fnirsi(&["parsed", "--dry-run", "tests/fixtures/sine_1khz.bin", "tests/fixtures/dc_levels.bin"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "{}", stderr);
+    assert!(output.stdout.is_empty());
+    assert!(stderr.contains("tests/fixtures/sine_1khz.bin: ok"));
+    assert!(stderr.contains("tests/fixtures/dc_levels.bin: ok"));
+
+    let output = fnirsi(&["parsed", "--dry-run", "tests/fixtures/sine_1khz.bin", "tests/fixtures/missing.bin"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("tests/fixtures/missing.bin: failed"));
+}
+
+#[test]
+fn forced_layouts_override_the_detected_one() {
+    let raw = json(&["raw", "--force-4ch", "tests/fixtures/four_channel.bin"]);
+
+    for buffer in ["channel11", "channel21", "channel31", "channel41"] {
+        assert_eq!(raw[buffer].as_array().unwrap().len(), 1500, "{}", buffer);
+    }
+    for buffer in ["channel12", "channel22", "channel32", "channel42"] {
+        assert_eq!(raw[buffer].as_array().unwrap().len(), 750, "{}", buffer);
+    }
+
+    let raw = json(&["raw", "--force-2ch", "tests/fixtures/four_channel.bin"]);
+    assert!(raw.get("channel31").is_none());
+
+    let output = fnirsi(&["raw", "--force-4ch", "tests/fixtures/sine_1khz.bin"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("a four channel capture has 19000"), "{}", stderr);
+}
+
+#[cfg(feature = "plotters")]
+#[test]
+fn chart_time_axis_spans_the_points_in_the_time_unit() {
+    let path = std::env::temp_dir().join("fnirsi-chart-time-axis.svg");
+    let output = fnirsi(&["chart-svg", "--select-time-unit", "us", "--time-origin", "0.001", "--output-file", path.to_str().unwrap(), "tests/fixtures/sine_1khz.bin"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let svg = std::fs::read_to_string(&path).unwrap();
+
+    // The 1500 points 2 us apart shifted by 1 ms span 1000 us to 3998 us
+    assert!(svg.contains("Time (us)"), "{}", svg);
+    assert!(svg.contains("1000 us"), "{}", svg);
+    assert!(svg.contains("1299.8 us"), "{}", svg);
+    assert!(svg.contains("3998 us"), "{}", svg);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "sigrok")]
+#[test]
+fn sigrok_output_holds_the_digitized_channels() {
+    use std::io::Read;
+
+    let path = std::env::temp_dir().join("fnirsi-sigrok-output.sr");
+    let output = fnirsi(&["sigrok", "--digitize", "ch1=1.5,0.5", "--output-file", path.to_str().unwrap(), "tests/fixtures/sine_1khz.bin"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let mut read = |name: &str| {
+        let mut contents = Vec::new();
+        archive.by_name(name).unwrap().read_to_end(&mut contents).unwrap();
+        contents
+    };
+    let metadata = String::from_utf8(read("metadata")).unwrap();
+    let logic = read("logic-1-1");
+
+    assert_eq!(read("version"), b"2");
+    assert!(metadata.contains("samplerate=500000 Hz\n"), "{}", metadata);
+    assert!(metadata.contains("total samples=1500\n"), "{}", metadata);
+    assert!(metadata.contains("total probes=1\n"), "{}", metadata);
+    assert!(metadata.contains("probe1=CH1\n"), "{}", metadata);
+    assert!(!metadata.contains("CH2"));
+    assert_eq!(logic.len(), 1500);
+    // Low from 0 V at the start, high on the crest of 2 V and low again in the trough of -2 V
+    assert_eq!(logic[0], 0);
+    assert_eq!(logic[125], 1);
+    assert_eq!(logic[375], 0);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn vcd_output_dumps_the_level_changes() {
+    let path = std::env::temp_dir().join("fnirsi-vcd-output.vcd");
+    let output = fnirsi(&["vcd", "--output-file", path.to_str().unwrap(), "tests/fixtures/sine_1khz.bin"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let vcd = std::fs::read_to_string(&path).unwrap();
+    let changes = vcd.split("$enddefinitions $end\n").nth(1).unwrap().lines().take(11).collect::<Vec<_>>();
+
+    assert!(vcd.contains("$timescale 1ns $end\n"));
+    assert!(vcd.contains("$var wire 1 ! CH1 $end\n"));
+    assert!(vcd.contains("$var wire 1 \" CH2 $end\n"));
+    // Channel 1 is low on 0 V at the start and crosses its midpoint every 250 samples of 2 us,
+    // channel 2 starts high and switches every 500 samples
+    assert_eq!(changes, ["#0", "$dumpvars", "0!", "1\"", "$end", "#2000", "1!", "#502000", "0!", "#1000000", "0\""]);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn columnar_points_are_parallel_arrays() {
+    let parsed = json(&["parsed", "--columnar-points", "--differential", "tests/fixtures/sine_1khz.bin"]);
+
+    for channel in ["channel1", "channel2", "differential"] {
+        let (times, voltages) = (parsed[channel]["times"].as_array().unwrap(), parsed[channel]["voltages"].as_array().unwrap());
+
+        assert_eq!(times.len(), 1500, "{}", channel);
+        assert_eq!(times.len(), voltages.len(), "{}", channel);
+        assert!(parsed[channel].get("points").is_none(), "{}", channel);
+    }
+    assert_eq!(parsed["channel1"]["times"][1], 2e-6);
+    assert_eq!(parsed["channel1"]["voltages"][1], 0.02);
+}
+
+#[test]
+fn bode_plot_output_sweeps_the_batch() {
+    let directory = std::env::temp_dir().join("fnirsi-bode-plot-batch");
+    std::fs::create_dir_all(&directory).unwrap();
+    // Channel 2 follows channel 1 at a quarter of its amplitude, a whole number of periods at 100us/div
+    for (name, frequency_hz) in [("sweep_2kHz.bin", 2000.0), ("sweep_1kHz.bin", 1000.0), ("other_4kHz.bin", 4000.0)] {
+        let samples = |amplitude: f64| (0..1500)
+            .map(|index| (512.0 + amplitude * (2.0 * std::f64::consts::PI * frequency_hz * index as f64 * 2e-6).sin()).round() as u16)
+            .collect();
+        let bytes = fnirsi::FileBuilder::new()
+            .time_scale(fnirsi::Scale { value: 100.0, scale: -6, unit: fnirsi::Second })
+            .channel1_data(samples(200.0))
+            .channel2_data(samples(50.0))
+            .build().unwrap();
+        std::fs::write(directory.join(name), bytes).unwrap();
+    }
+
+    let pattern = directory.join("sweep_*.bin");
+    let bode = json(&["bode-plot", "--batch", pattern.to_str().unwrap(), "--input-ch", "1", "--output-ch", "2"]);
+    let points = bode["points"].as_array().unwrap();
+
+    assert_eq!(points.iter().map(|point| point["frequency_hz"].as_f64().unwrap()).collect::<Vec<_>>(), [1000.0, 2000.0]);
+    assert!((points[0]["gain_db"].as_f64().unwrap() + 12.04).abs() < 0.1, "{}", bode);
+    assert!(points[1]["phase_deg"].as_f64().unwrap().abs() < 1.0, "{}", bode);
+
+    let output = fnirsi(&["bode-plot", "--batch", directory.join("missing_*.bin").to_str().unwrap()]);
+    assert!(!output.status.success());
+    let output = fnirsi(&["parsed", "--batch", pattern.to_str().unwrap(), "--output-ch", "2"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only apply to the bode-plot output"));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
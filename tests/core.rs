@@ -0,0 +1,24 @@
+//! Parses a capture with the core of the crate alone, which builds without the `std` feature:
+//! `cargo test --no-default-features --test core`
+
+use std::convert::TryFrom;
+
+use fnirsi::*;
+
+const SINE: &[u8] = include_bytes!("fixtures/sine_1khz.bin");
+
+#[test]
+fn parses_a_capture_from_its_bytes() {
+    let file = parse_file(SINE).unwrap();
+    let (scale, time_scale) = (Scale::<Volt>::try_from(file.header.channel1_scale).unwrap(), Scale::<Second>::try_from(file.header.time_scale).unwrap());
+    let points = generate_points(&file.channel11, &scale, &time_scale, file.header.channel1_offset);
+
+    assert_eq!(points.len(), 1500);
+    // The crest of the 1 kHz sine a quarter period of 250 samples of 2 us in, 100 counts above the offset at 1V/div
+    assert!((points[125].voltage - 2.0).abs() < 1e-3, "{:?}", points[125]);
+    assert!((points[125].time - 250e-6).abs() < 1e-9, "{:?}", points[125]);
+    assert_eq!(sample_rate_hz(&time_scale), 500e3);
+    assert_eq!(parse_frequency(file.header.channel1_measurements.frequency_high, file.header.channel1_measurements.frequency_low), 1000);
+    assert!((process_voltage_measurement(file.header.channel1_measurements.vpp) - 4.0).abs() < 1e-6);
+    assert!(parse_file(&SINE[..2000]).is_err());
+}
@@ -0,0 +1,62 @@
+//! Checks the errors keep their causes for embedders
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::path::Path;
+
+use fnirsi::*;
+
+#[test]
+fn io_errors_are_the_source() {
+    let error = read_calibration_file(Path::new("tests/fixtures/missing.toml")).unwrap_err();
+    let source = error.source().unwrap().downcast_ref::<std::io::Error>().unwrap();
+
+    assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn conversion_errors_are_the_source() {
+    let error = FnirsiError::from(Coupling::try_from(7).unwrap_err());
+
+    assert!(matches!(error, FnirsiError::InvalidCoupling(_)));
+    assert!(error.source().unwrap().to_string().contains('7'));
+
+    let error = FnirsiError::from(parse_file(&[0; 16]).unwrap_err());
+
+    assert!(error.source().unwrap().downcast_ref::<binread::Error>().is_some());
+}
+
+#[test]
+fn unknown_time_scales_are_errors() {
+    let mut file = parse_file(include_bytes!("fixtures/sine_1khz.bin")).unwrap();
+    file.header.time_scale = TIME_SCALES.len() as u16;
+    let hundred_micros = Scale { value: 100.0, scale: -6, unit: Second };
+
+    match CaptureContext::new(file.clone(), ParseOptions::default()).time_scale() {
+        Err(error @ FnirsiError::InvalidTimeScale(_)) => assert!(error.source().unwrap().to_string().contains(&TIME_SCALES.len().to_string())),
+        other => panic!("Expected an invalid time scale, got {:?}", other)
+    }
+    // The validation of the header finds it first
+    assert!(matches!(validate_capture(&CaptureContext::new(file.clone(), ParseOptions::default())), Err(FnirsiError::InvalidHeaderValue { field: "time_scale", .. })));
+    let context = CaptureContext::new(file, ParseOptions::builder().time_scale_override(hundred_micros).build());
+    assert_eq!(context.time_scale().unwrap(), hundred_micros);
+    assert_eq!(parse_data(&context).unwrap().time_scale, hundred_micros);
+}
+
+#[test]
+fn scales_missing_from_the_tables_fail_the_file_builder() {
+    let three_volts = Scale { value: 3.0, scale: 0, unit: Volt };
+    let three_micros = Scale { value: 3.0, scale: -6, unit: Second };
+    let one_volt = Scale { value: 1.0, scale: 0, unit: Volt };
+
+    for builder in [FileBuilder::new().channel1_scale(three_volts), FileBuilder::new().channel2_scale(three_volts).channel1_scale(one_volt)] {
+        match builder.build() {
+            Err(error @ FnirsiError::ScaleNotInTable(_)) => assert_eq!(error.to_string(), "The scale 3V is not in the scale tables of the header"),
+            other => panic!("Expected a scale missing from the tables, got {:?}", other.map(|bytes| bytes.len()))
+        }
+    }
+    assert!(matches!(FileBuilder::new().time_scale(three_micros).build(), Err(FnirsiError::ScaleNotInTable(scale)) if scale == "3us"));
+
+    let bytes = FileBuilder::new().channel1_scale(one_volt).build().unwrap();
+    assert_eq!(parse_file(&bytes).unwrap().header.channel1_scale, probe_scale_to_index(&one_volt).unwrap());
+}
@@ -0,0 +1,648 @@
+//! Parses the captures under `tests/fixtures`:
+//!
+//! - `sine_1khz.bin`: 100us/div, channel 1 a 1 kHz sine of 100 counts at 1V/div DC with a 1x probe and offset 400,
+//!   channel 2 a 500 Hz square of 50 counts at 500mV/div AC with a 10x probe and offset 600
+//! - `dc_levels.bin`: 1ms/div, channel 1 flat 50 counts above its offset 400 at 200mV/div,
+//!   channel 2 flat 25 counts below its offset 512 at 1V/div
+//! - `clipped.bin`: `sine_1khz.bin` with channel 1 a 1 kHz sine of 300 counts around its offset 512,
+//!   saturating at the edges of the screen 200 counts from it
+//! - `four_channel.bin`: `sine_1khz.bin` followed by the buffers of channel 3, a ramp from 312 to 411 every 100 samples,
+//!   and channel 4, flat at 700
+
+use std::convert::TryFrom;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use fnirsi::*;
+
+const SINE: &[u8] = include_bytes!("fixtures/sine_1khz.bin");
+const DC_LEVELS: &[u8] = include_bytes!("fixtures/dc_levels.bin");
+const CLIPPED: &[u8] = include_bytes!("fixtures/clipped.bin");
+const FOUR_CHANNEL: &[u8] = include_bytes!("fixtures/four_channel.bin");
+
+fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+    assert!((actual - expected).abs() <= tolerance, "{} is not within {} of {}", actual, tolerance, expected);
+}
+
+fn max_voltage(points: &[Point]) -> f32 {
+    points.iter().map(|point| point.voltage).fold(f32::MIN, f32::max)
+}
+
+fn min_voltage(points: &[Point]) -> f32 {
+    points.iter().map(|point| point.voltage).fold(f32::MAX, f32::min)
+}
+
+#[test]
+fn parses_the_header_alone() {
+    // Everything up to the first sample buffer
+    let header = parse_header(Cursor::new(&SINE[..CHANNEL_DATA_OFFSET as usize])).unwrap();
+
+    assert_eq!(header.channel1_scale, 2);
+    assert_eq!(header.time_scale, 17);
+    assert_eq!(header.channel1_offset, 400);
+    assert_eq!(header.channel2_offset, 600);
+    assert_eq!(header.channel1_measurements.vpp, 4096);
+    assert_eq!(header.channel2_measurements.frequency_low, 500);
+    assert!(parse_file(&SINE[..CHANNEL_DATA_OFFSET as usize]).is_err());
+    // Cut off in the measurements of channel 1
+    assert!(matches!(parse_header(Cursor::new(&SINE[..220])), Err(FnirsiError::Parse(_))));
+}
+
+#[test]
+fn resolves_a_count_of_the_adc() {
+    let data = parse_capture(SINE, ParseOptions::default()).unwrap();
+
+    // 50 counts per division
+    assert_close(data.channel1.voltage_resolution_v, 0.02, 1e-9);
+    assert_close(data.channel2.voltage_resolution_v, 0.1, 1e-9);
+    assert_eq!(data.channel2.voltage_resolution_v, data.channel2.probe.voltage_resolution_v());
+}
+
+#[test]
+fn scales_behind_a_hundred_times_probe() {
+    let bytes = FileBuilder::new()
+        .channel1_scale(Scale { value: 5.0, scale: 0, unit: Volt })
+        .channel1_attenuation(Attenuation::OneHundredX)
+        .channel1_data(vec![ADC_MIDPOINT + 50; 1500])
+        .build().unwrap();
+    let data = parse_capture(&bytes, ParseOptions::default()).unwrap();
+
+    assert_eq!(data.channel1.probe.attenuation, Attenuation::OneHundredX);
+    assert_eq!(data.channel1.probe.scale.to_string(), "5V");
+    assert_eq!(data.channel1.effective_scale.to_string(), "500V");
+    assert_close(data.channel1.effective_scale.get_scale(), 500.0, 1e-3);
+    // One division above the offset
+    assert_close(data.channel1.points[0].voltage, 500.0, 1e-2);
+}
+
+#[test]
+fn warns_about_the_placeholder_scales() {
+    use std::sync::Mutex;
+
+    // Keeps the warnings of the whole test binary, the other tests warn as well
+    struct Warnings(Mutex<Vec<String>>);
+
+    impl log::Log for Warnings {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static WARNINGS: Warnings = Warnings(Mutex::new(Vec::new()));
+    log::set_logger(&WARNINGS).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+
+    let table = [
+        ScaleEntry::from(Scale { value: 1.0, scale: 0, unit: Volt }),
+        ScaleEntry::placeholder(Scale { value: 2.0, scale: 0, unit: Volt })
+    ];
+    let placeholder = "probe scale index 1 is a placeholder, the scale 2V is a guess";
+
+    assert_eq!(lookup_scale(&table, 0, "probe scale").unwrap().to_string(), "1V");
+    assert!(!WARNINGS.0.lock().unwrap().iter().any(|warning| warning.starts_with("probe scale index")));
+    assert_eq!(lookup_scale(&table, 1, "probe scale").unwrap().to_string(), "2V");
+    assert!(WARNINGS.0.lock().unwrap().iter().any(|warning| warning == placeholder));
+    assert!(lookup_scale(&table, 2, "probe scale").is_none());
+}
+
+#[test]
+fn marks_the_points_on_the_grid_lines() {
+    let data = parse_capture(SINE, ParseOptions::builder().annotate_divisions(true).build()).unwrap();
+    let markers = data.channel1.division_markers.unwrap();
+
+    assert_eq!(markers, (0..1500).step_by(50).collect::<Vec<_>>());
+    assert_eq!(markers.len(), 30);
+    assert_eq!(data.channel2.division_markers.unwrap()[..3], [0, 50, 100]);
+    assert!(parse_capture(SINE, ParseOptions::default()).unwrap().channel1.division_markers.is_none());
+    // Divisions of 2 points
+    assert_eq!(division_markers(5, 2.0), [0, 2, 4]);
+    assert!(division_markers(5, 0.0).is_empty());
+}
+
+#[test]
+fn writes_the_tektronix_csv_of_a_channel() {
+    let data = parse_capture(SINE, ParseOptions::default()).unwrap();
+    let mut csv = Vec::new();
+    write_tektronix_csv(&data, 2, &mut csv).unwrap();
+    let rows = String::from_utf8(csv).unwrap().lines().map(|row| row.split(',').map(str::to_string).collect::<Vec<_>>()).collect::<Vec<_>>();
+    let labels = rows.iter().map(|row| row[0].as_str()).filter(|label| !label.is_empty()).collect::<Vec<_>>();
+    let value = |label: &str| rows.iter().find(|row| row[0] == label).unwrap()[1].clone();
+
+    assert_eq!(labels, [
+        "Record Length", "Sample Interval", "Trigger Point", "Source", "Vertical Units", "Vertical Scale", "Vertical Offset",
+        "Horizontal Units", "Horizontal Scale", "Pt Fmt", "Yzero", "Probe Atten", "Model Number", "Serial Number", "Firmware Version"
+    ]);
+    assert_eq!(value("Record Length"), "1.500000e+03");
+    assert_eq!(value("Sample Interval"), "2.000000e-06");
+    assert_eq!(value("Source"), "CH2");
+    assert_eq!(value("Vertical Scale"), "5.00e+00");
+    assert_eq!(value("Probe Atten"), "1.00e+01");
+    assert_eq!(rows.len(), 1500);
+    assert!(rows.iter().all(|row| row.len() == 6 && row[2].is_empty() && row[5].is_empty()));
+    // The square is low from its 500th sample on
+    assert_eq!(rows[1][3..5], ["2.000000e-06", "5.0000e+00"]);
+    assert_eq!(rows[500][4], "-5.0000e+00");
+    assert_close(rows[500][3].parse().unwrap(), 1e-3, 1e-9);
+    assert!(matches!(write_tektronix_csv(&data, 3, &mut Vec::new()), Err(FnirsiError::UnknownChannel(3))));
+}
+
+#[test]
+fn builds_the_parse_options() {
+    let options = ParseOptions::builder()
+        .buffer(Buffer::Zoom)
+        .attenuation_override([Attenuation::TenX, Attenuation::OneX])
+        .time_scale_override(Scale { value: 200.0, scale: -6, unit: Second })
+        .min_samples(700)
+        .build();
+
+    assert_eq!(options.buffer, Buffer::Zoom);
+    assert_eq!(options.min_samples, Some(700));
+    assert_eq!(ParseOptions::builder().build().min_samples, ParseOptions::default().min_samples);
+
+    let data = parse_capture(SINE, options).unwrap();
+    // 750 samples of the zoom buffer 4 us apart, channel 1 behind a 10x probe and channel 2 behind none
+    assert_eq!(data.channel1.points.len(), 750);
+    assert_close(data.channel1.points[1].time, 4e-6, 1e-12);
+    assert_eq!(data.channel1.effective_scale.to_string(), "10V");
+    assert_eq!(data.channel2.effective_scale.to_string(), "500mV");
+}
+
+#[test]
+fn calibrates_only_channels_1_and_2() {
+    let calibration = Calibration { gain: 2.0, offset_v: 0.5 };
+    let options = ParseOptions::builder().calibration(2, calibration).unwrap().build();
+
+    assert_eq!(options.calibration, [Calibration::default(), calibration]);
+    // The 5 V of the square doubled and raised by 0.5 V
+    assert_close(parse_capture(SINE, options).unwrap().channel2.points[0].voltage, 10.5, 1e-3);
+    for channel in [0, 3, u8::MAX] {
+        match ParseOptions::builder().calibration(channel, calibration) {
+            Err(error @ FnirsiError::UnknownChannel(_)) =>
+                assert_eq!(error.to_string(), format!("There is no channel {}, the capture has channels 1 and 2", channel)),
+            other => panic!("Expected an unknown channel, got {:?}", other.map(|builder| builder.build()))
+        }
+    }
+}
+
+#[test]
+fn digitizes_only_channels_1_and_2() {
+    let thresholds = LogicThresholds { high_v: 1.5, low_v: 0.5 };
+    let data = parse_capture(SINE, ParseOptions::builder().digitize(1, thresholds).unwrap().build()).unwrap();
+    let levels = data.channel1_digital.unwrap();
+
+    // Low from 0 V at the start, high on the crest of 2 V and low again in the trough of -2 V
+    assert_eq!(levels.len(), 1500);
+    assert!(!levels[0] && levels[125] && !levels[375]);
+    assert!(data.channel2_digital.is_none());
+    for channel in [0, 3, u8::MAX] {
+        assert!(matches!(ParseOptions::builder().digitize(channel, thresholds), Err(FnirsiError::UnknownChannel(unknown)) if unknown == channel));
+    }
+}
+
+#[test]
+fn decodes_the_cycle_time_of_a_known_frequency() {
+    // A 20 kHz sine, whose 50 us period and pulse widths fit in the 16 bits of the fields
+    let bytes = FileBuilder::new()
+        .time_scale(Scale { value: 100.0, scale: -6, unit: Second })
+        .channel1_data((0..1500).map(|index| (512.0 + 100.0 * (2.0 * std::f64::consts::PI * 20e3 * index as f64 * 2e-6).sin()).round() as u16).collect())
+        .channel1_measurements(Measurements {
+            frequency_low: 20000,
+            cycle_ns: 50000,
+            time_plus_ns: 25000,
+            time_minus_ns: 25000,
+            duty_plus_percentage: 50,
+            duty_minus_percentage: 50,
+            ..Measurements::default()
+        })
+        .build().unwrap();
+    let data = parse_capture(&bytes, ParseOptions::default()).unwrap();
+    let measurements = &data.channel1.measurements;
+
+    assert_eq!(decode_cycle_time(50000), 50e-6);
+    assert_eq!(measurements.frequency, 20000);
+    assert!((1.0 / decode_cycle_time(measurements.cycle_ns.raw) - measurements.frequency as f64).abs() < 1e-6);
+    assert!((cycle_time_us(measurements) - 50.0).abs() < 1e-9);
+    assert!((time_plus_us(measurements) + time_minus_us(measurements) - cycle_time_us(measurements)).abs() < 1e-9);
+    assert!(period_consistent(measurements, 0.0));
+    // The period of the samples agrees
+    assert!((1.0 / period_estimation_fft(&data.channel1.points, 500e3).unwrap() - 20e3).abs() < 20.0);
+}
+
+#[test]
+fn clips_at_the_divisions_of_the_screen() {
+    let data = parse_capture(SINE, ParseOptions::default()).unwrap();
+    let differential = |vertical_divisions: f32| differential_channel(&data.channel1, &data.channel2, vertical_divisions).unwrap();
+    let quality = |vertical_divisions: f32| channel_quality(&data.channel1.points, &data.channel1.effective_scale, &data.channel1.measurements, vertical_divisions);
+    let top_row = |vertical_divisions: f32| render_ascii(&data, 40, 10, vertical_divisions).lines().nth(1).unwrap().to_string();
+
+    // The 7 V peaks of the difference fit 2V/div on 8 divisions, 5V/div on 4 and 1V/div on 16
+    assert_eq!(differential(VERTICAL_DIVISIONS).effective_scale.to_string(), "2V");
+    assert_eq!(differential(4.0).effective_scale.to_string(), "5V");
+    assert_eq!(differential(16.0).effective_scale.to_string(), "1V");
+    // The crests of the 2 V sine at 1V/div hit the top and bottom of a screen of 4 divisions
+    assert_eq!(quality(VERTICAL_DIVISIONS).clipping_fraction, 0.0);
+    assert!(quality(4.0).clipping_fraction > 0.0);
+    assert_eq!(quality(VERTICAL_DIVISIONS).clipping_fraction, data.channel1.quality.clipping_fraction);
+    assert!(top_row(VERTICAL_DIVISIONS).is_empty());
+    assert!(top_row(4.0).contains('█'));
+    assert_close(data.channel1.probe.full_scale_range_v(VERTICAL_DIVISIONS), 8.0, 1e-6);
+    assert_close(data.channel2.probe.full_scale_range_v(4.0), 20.0, 1e-6);
+}
+
+#[test]
+fn parses_the_raw_header() {
+    let file = parse_file(SINE).unwrap();
+
+    assert_eq!(file.header.channel1_scale, 2);
+    assert_eq!(file.header.channel2_scale, 3);
+    assert_eq!(file.header.channel2_coupling, 1);
+    assert_eq!(file.header.channel2_probe, 1);
+    assert_eq!(file.header.time_scale, 17);
+    assert_eq!(file.header.channel1_offset, 400);
+    assert_eq!(file.header.channel2_offset, 600);
+    assert_eq!(file.header.channel1_measurements.vpp, 4096);
+    assert_eq!(file.header.channel1_measurements.frequency_low, 1000);
+    assert_eq!(file.header.channel2_measurements.frequency_low, 500);
+    assert_eq!(file.channel11.len(), 1500);
+    assert_eq!(file.channel21.len(), 1500);
+    assert_eq!(file.channel12.len(), 750);
+    assert_eq!(file.channel22.len(), 750);
+    assert_eq!(file.channel11[0], 400);
+    assert_eq!(file.channel21[0], 650);
+}
+
+#[test]
+fn looks_up_the_scales() {
+    let one_volt = Scale::<Volt>::try_from(2).unwrap();
+    let hundred_micros = Scale::<Second>::try_from(17).unwrap();
+
+    assert_eq!(one_volt.to_string(), "1V");
+    assert_eq!(Scale::<Volt>::try_from(6).unwrap().to_string(), "50mV");
+    assert_eq!(hundred_micros.to_string(), "100us");
+    assert_eq!(Scale::<Second>::try_from(0).unwrap().to_string(), "50s");
+    assert_close(hundred_micros.get_scale(), 100e-6, 1e-12);
+    assert_eq!(probe_scale_to_index(&one_volt), Some(2));
+    assert_eq!(time_scale_to_index(&hundred_micros), Some(17));
+    assert!(Scale::<Volt>::try_from(PROBE_SCALES.len() as u16).is_err());
+    assert!(Scale::<Second>::try_from(TIME_SCALES.len() as u16).is_err());
+}
+
+#[test]
+fn parses_the_enum_names() {
+    assert_eq!("dc".parse(), Ok(Coupling::DC));
+    assert_eq!("AC".parse(), Ok(Coupling::AC));
+    assert_eq!("auto".parse(), Ok(TriggerType::Auto));
+    assert_eq!("Single".parse(), Ok(TriggerType::Single));
+    assert_eq!("rising".parse(), Ok(TriggerEdge::Rising));
+    assert_eq!("10x".parse(), Ok(Attenuation::TenX));
+    assert_eq!(Attenuation::OneHundredX.to_string().parse(), Ok(Attenuation::OneHundredX));
+    assert_eq!("20x".parse::<Attenuation>(), Err(InvalidEnumValue { value: "20x".to_string(), enum_name: "Attenuation" }));
+}
+
+#[test]
+fn selects_the_time_unit_of_the_time_scale() {
+    let twenty_nanos = Scale { value: 20.0, scale: -9, unit: Second };
+    let one_second = Scale::<Second>::try_from(5).unwrap();
+
+    assert_eq!(TimeUnit::for_scale(&twenty_nanos), TimeUnit::Nanoseconds);
+    assert_eq!(serde_json::to_value(TimeUnit::for_scale(&twenty_nanos)).unwrap(), "ns");
+    assert_eq!(TimeUnit::for_scale(&one_second), TimeUnit::Seconds);
+    assert_eq!(serde_json::to_value(TimeUnit::for_scale(&one_second)).unwrap(), "s");
+
+    let data = parse_capture(SINE, ParseOptions::builder().time_unit(TimeUnitSelection::Auto).build()).unwrap();
+
+    assert_eq!(data.time_unit, Some(TimeUnit::Microseconds));
+    assert_close(data.channel1.points[1499].time, 2998.0, 1e-3);
+}
+
+#[test]
+fn scales_the_points_and_measurements() {
+    let data = parse_capture(SINE, ParseOptions::default()).unwrap();
+
+    assert_eq!(data.time_scale.to_string(), "100us");
+    assert_eq!(data.channel1.probe.coupling, Coupling::DC);
+    assert_eq!(data.channel1.effective_scale.to_string(), "1V");
+    assert_eq!(data.channel1.points.len(), 1500);
+    assert_close(max_voltage(&data.channel1.points), 2.0, 1e-3);
+    assert_close(min_voltage(&data.channel1.points), -2.0, 1e-3);
+    assert_close(data.channel1.points[1499].time, 1499.0 * 2e-6, 1e-9);
+
+    let measurements = &data.channel1.measurements;
+    assert_close(measurements.vmax, 2.0, 1e-3);
+    assert_close(measurements.vpp, 4.0, 1e-3);
+    assert_close(measurements.vrms, 1.414, 1e-3);
+    assert_eq!(measurements.frequency, 1000);
+    assert_eq!(measurements.duty_plus_percentage, 50);
+}
+
+#[test]
+fn builds_channel2_from_its_own_samples() {
+    let data = parse_capture(SINE, ParseOptions::default()).unwrap();
+
+    assert_eq!(data.channel2.probe.coupling, Coupling::AC);
+    assert_eq!(data.channel2.probe.attenuation, Attenuation::TenX);
+    assert_eq!(data.channel2.effective_scale.to_string(), "5V");
+    assert_close(max_voltage(&data.channel2.points), 5.0, 1e-3);
+    assert_close(min_voltage(&data.channel2.points), -5.0, 1e-3);
+    assert_close(data.channel2.measurements.vpp, 10.0, 1e-3);
+    assert_eq!(data.channel2.measurements.frequency, 500);
+
+    let dc = parse_capture(DC_LEVELS, ParseOptions::default()).unwrap();
+
+    assert!(dc.channel1.points.iter().all(|point| (point.voltage - 0.2).abs() < 1e-4));
+    assert!(dc.channel2.points.iter().all(|point| (point.voltage + 0.5).abs() < 1e-4));
+    assert_eq!(dc.channel1.signal_class, SignalClass::Dc);
+    assert_eq!(dc.channel2.signal_class, SignalClass::Dc);
+}
+
+#[test]
+fn scales_the_offsets_from_the_center_of_the_screen() {
+    let dc = parse_capture(DC_LEVELS, ParseOptions::default()).unwrap();
+    let sine = parse_capture(SINE, ParseOptions::default()).unwrap();
+
+    assert_close(dc.channel2.offset_v, 0.0, 1e-6);
+    // 112 counts below the center at 4 mV per count
+    assert_close(dc.channel1.offset_v, -0.448, 1e-4);
+    // 88 counts above the center at 100 mV per count behind the 10x probe
+    assert_close(sine.channel2.offset_v, 8.8, 1e-3);
+}
+
+#[test]
+fn annotates_the_measured_extremes() {
+    let data = parse_capture(SINE, ParseOptions::default()).unwrap();
+    let annotated = annotate_measurements(&data.channel1.points, &data.channel1.measurements);
+    let indices = |prefix: &str| annotated.iter()
+        .enumerate()
+        .filter(|(_, point)| point.annotations.iter().any(|annotation| annotation.starts_with(prefix)))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    assert_eq!(annotated.len(), 1500);
+    // The first sample rounded to the crest at 125 of the 500 sample period
+    assert_eq!(indices("Vmax"), [118]);
+    assert_eq!(annotated[118].annotations, ["Vmax 2 V"]);
+    // Through the midpoint of 1 V between the measured 2 V and 0 V
+    assert_eq!(indices("Rising edge"), [42, 542, 1042]);
+}
+
+#[test]
+fn subtracts_the_channels_at_the_probe_tips() {
+    let data = parse_capture(SINE, ParseOptions::builder().differential(true).build()).unwrap();
+    let differential = data.differential.unwrap();
+
+    assert_eq!(differential.probe.coupling, Coupling::DC);
+    assert_eq!(differential.probe.attenuation, Attenuation::OneX);
+    // The swing of 7 V doesn't fit the 1V/div of channel 1, 2V/div is the finest scale it fits
+    assert_eq!(differential.effective_scale.to_string(), "2V");
+    assert_close(differential.points[0].voltage, -5.0, 1e-3);
+    assert_close(differential.measurements.vmax, 7.0, 1e-3);
+    assert_close(differential.measurements.vmin, -7.0, 1e-3);
+    assert!(differential.points.iter().zip(&data.channel1.points).zip(&data.channel2.points)
+        .all(|((difference, point1), point2)| (difference.voltage - (point1.voltage - point2.voltage)).abs() < 1e-6));
+}
+
+#[test]
+fn names_unknown_probe_values() {
+    let mut bytes = SINE.to_vec();
+    bytes[10] = 7;
+
+    match parse_capture(&bytes, ParseOptions::default()) {
+        Err(error @ FnirsiError::UnknownAttenuation { channel: 1, value: 7 }) =>
+            assert_eq!(error.to_string(), "Channel 1 probe value 7 is not a known attenuation"),
+        other => panic!("Expected an unknown attenuation, got {:?}", other)
+    }
+
+    let data = parse_capture(&bytes, ParseOptions::builder().lenient(true).build()).unwrap();
+
+    assert_eq!(data.channel1.probe.attenuation, Attenuation::OneX);
+    assert_eq!(data.channel2.probe.attenuation, Attenuation::TenX);
+}
+
+#[test]
+fn detects_the_channel_layout() {
+    assert_eq!(ChannelLayout::detect(SINE), ChannelLayout::TwoChannel);
+    assert_eq!(ChannelLayout::detect(FOUR_CHANNEL), ChannelLayout::FourChannel);
+
+    let file = parse_file(FOUR_CHANNEL).unwrap();
+
+    assert_eq!(file.channel31.len(), 1500);
+    assert_eq!(file.channel42.len(), 750);
+    assert_eq!(file.channel31[..3], [312, 313, 314]);
+    assert_eq!(file.channel41[0], 700);
+    assert_eq!(file.channel21, parse_file(SINE).unwrap().channel21);
+    assert!(parse_file(SINE).unwrap().channel31.is_empty());
+    assert!(parse_file_with_layout(FOUR_CHANNEL, ChannelLayout::TwoChannel).unwrap().channel31.is_empty());
+    assert!(parse_file_with_layout(SINE, ChannelLayout::FourChannel).is_err());
+}
+
+#[test]
+fn warns_about_the_clipped_points() {
+    let data = parse_capture(CLIPPED, ParseOptions::default()).unwrap();
+    let codes = data.warnings.iter().map(|warning| warning.split(':').next().unwrap()).collect::<Vec<_>>();
+
+    assert_eq!(codes, ["clipping"]);
+    assert!(data.warnings[0].starts_with("clipping: channel 1 "), "{}", data.warnings[0]);
+    assert!(parse_capture(SINE, ParseOptions::default()).unwrap().warnings.is_empty());
+    assert!(parse_capture(DC_LEVELS, ParseOptions::default()).unwrap().warnings.is_empty());
+}
+
+#[test]
+fn marks_the_trigger_source() {
+    let data = parse_capture(SINE, ParseOptions::builder().differential(true).build()).unwrap();
+    let sources = [&data.channel1, &data.channel2].iter().filter(|channel| channel.is_trigger_source).count();
+
+    assert_eq!(sources, 1);
+    assert!(data.channel1.is_trigger_source);
+    assert!(!data.differential.unwrap().is_trigger_source);
+
+    let mut bytes = SINE.to_vec();
+    bytes[30] = 1;
+    let data = parse_capture(&bytes, ParseOptions::default()).unwrap();
+
+    assert!(!data.channel1.is_trigger_source);
+    assert!(data.channel2.is_trigger_source);
+}
+
+#[test]
+fn canonicalizes_the_json() {
+    let json = to_canonical_json(&parse_capture(SINE, ParseOptions::default()).unwrap()).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+    let mut sorted = keys.clone();
+    sorted.sort();
+
+    assert_eq!(json, to_canonical_json(&parse_capture(SINE, ParseOptions::default()).unwrap()).unwrap());
+    assert_eq!(keys, sorted);
+    assert!(json.starts_with(r#"{"channel1":{"attenuation":"OneX","coupling":"DC","#), "{}", &json[..80]);
+    // The f32 values keep their shortest decimal
+    assert!(json.contains(r#"{"time":2e-6,"voltage":0.02}"#));
+}
+
+#[test]
+fn warns_about_a_vpp_disagreeing_with_the_extremes() {
+    let mut bytes = DC_LEVELS.to_vec();
+    // A vpp of 2 V on channel 1, flat at 200 mV
+    bytes[226..228].copy_from_slice(&2048u16.to_le_bytes());
+    let data = parse_capture(&bytes, ParseOptions::default()).unwrap();
+
+    assert_eq!(data.warnings, ["vpp_mismatch: channel 1 vpp 2 V differs from vmax - vmin 0 V by more than 5%"]);
+    assert!(!vpp_consistent(&data.channel1.measurements, MEASUREMENT_TOLERANCE));
+    assert!(vpp_consistent(&data.channel2.measurements, MEASUREMENT_TOLERANCE));
+    // The 0 V minimum of the sine swinging 2 V below its offset is how the unsigned field reads
+    assert!(vpp_consistent(&parse_capture(SINE, ParseOptions::default()).unwrap().channel1.measurements, MEASUREMENT_TOLERANCE));
+}
+
+#[test]
+fn checks_the_consistency_within_the_tolerance() {
+    let mut bytes = SINE.to_vec();
+    // Duty cycles of 53% and 50% on channel 1, 3% more than the 100% they should add up to
+    bytes[250..252].copy_from_slice(&53u16.to_le_bytes());
+    let warnings = |tolerance| parse_capture(&bytes, ParseOptions::builder().tolerance(tolerance).build()).unwrap().warnings;
+
+    assert!(warnings(0.05).is_empty(), "{:?}", warnings(0.05));
+    assert_eq!(warnings(0.01), ["duty_mismatch: channel 1 duty cycles 53% and 50% differ from a sum of 100% by more than 1%"]);
+    assert!(parse_capture(SINE, ParseOptions::builder().tolerance(0.0).build()).unwrap().warnings.is_empty());
+
+    let data = parse_capture(&bytes, ParseOptions::default()).unwrap();
+    assert!(duty_consistent(&data.channel1.measurements, 0.05));
+    assert!(!duty_consistent(&data.channel1.measurements, 0.01));
+    // 1 kHz is slower than the longest cycle time the field holds
+    assert!(period_consistent(&data.channel1.measurements, 0.0));
+}
+
+#[test]
+fn suggests_the_scales_the_signals_read_best_at() {
+    let data = parse_capture(SINE, ParseOptions::builder().suggest_scales(true).build()).unwrap();
+    let (scale, time_scale) = auto_scale_suggestion(&data.channel1.measurements);
+
+    // The 4 V swing spans 4 divisions at 1V/div, 1 kHz shows 5 cycles at 500us/div and 2 at 200us/div
+    assert_eq!(scale.to_string(), "1V");
+    assert_eq!(time_scale.to_string(), "500us");
+    // The 10 V swing of channel 2 is 1 V behind its 10x probe, 5 divisions at 200mV/div
+    assert_eq!(data.suggested_ch2_scale.as_deref(), Some("2V/div"));
+    assert_eq!(data.suggested_ch1_scale.as_deref(), Some("1V/div"));
+    assert_eq!(data.suggested_time_scale.as_deref(), Some("500us/div"));
+    assert!(parse_capture(SINE, ParseOptions::default()).unwrap().suggested_time_scale.is_none());
+}
+
+#[test]
+fn compares_the_files() {
+    let file = parse_file(SINE).unwrap();
+    let mut bytes = SINE.to_vec();
+    // The last sample of channel 1
+    bytes[3998] = 0;
+    let changed = parse_file(&bytes).unwrap();
+
+    assert_eq!(file, parse_file(SINE).unwrap());
+    assert_eq!(file.header, changed.header);
+    assert_ne!(file, changed);
+    assert_eq!(file.header, parse_file(FOUR_CHANNEL).unwrap().header);
+    assert_ne!(file, parse_file(FOUR_CHANNEL).unwrap());
+    assert_eq!(Scale::<Volt>::try_from(3).unwrap(), Scale { value: 500.0, scale: -3, unit: Volt });
+    assert_eq!(Scale { value: f32::NAN, scale: 0, unit: Volt }, Scale { value: f32::NAN, scale: 0, unit: Volt });
+}
+
+#[test]
+fn linearizes_the_roll_mode_buffer() {
+    // A ramp written into a circular buffer whose newest sample sits at index 599
+    let ramp: Vec<u16> = (0..1500).map(|index| 200 + index / 3).collect();
+    let mut circular = vec![0; 1500];
+    for (index, sample) in ramp.iter().enumerate() {
+        circular[(600 + index) % 1500] = *sample;
+    }
+    let capture = |samples: Vec<u16>, scroll_speed: ScrollSpeed| FileBuilder::new().channel1_data(samples).scroll_speed(scroll_speed).build().unwrap();
+    let voltages = |data: &Data| data.channel1.points.iter().map(|point| point.voltage).collect::<Vec<_>>();
+    let options = ParseOptions::builder().roll_write_pointer(599).build();
+
+    let rolled = parse_capture(&capture(circular.clone(), ScrollSpeed::Slow), options.clone()).unwrap();
+    let linear = parse_capture(&capture(ramp.clone(), ScrollSpeed::Slow), ParseOptions::default()).unwrap();
+
+    assert_eq!(linearize_roll_buffer(&circular, 599), ramp);
+    assert_eq!(linearize_roll_buffer(&circular, 2099), ramp);
+    assert_eq!(voltages(&rolled), voltages(&linear));
+    assert_eq!(rolled.channel1.points[1].time, linear.channel1.points[1].time);
+    // Outside of roll mode the buffer is read as it is
+    let fast = parse_capture(&capture(circular.clone(), ScrollSpeed::Fast), options).unwrap();
+    assert_eq!(fast.channel1.points[0].voltage, voltages(&rolled)[900]);
+}
+
+#[test]
+fn measures_the_bode_plot_of_a_sweep() {
+    // Channel 2 at half the amplitude of channel 1 and a quarter of a period behind it, a whole number of periods at 100us/div
+    let capture = |frequency_hz: f64| {
+        let samples = |amplitude: f64, phase: f64| (0..1500)
+            .map(|index| (512.0 + amplitude * (2.0 * std::f64::consts::PI * frequency_hz * index as f64 * 2e-6 - phase).sin()).round() as u16)
+            .collect();
+        let bytes = FileBuilder::new()
+            .time_scale(Scale { value: 100.0, scale: -6, unit: Second })
+            .channel1_data(samples(200.0, 0.0))
+            .channel2_data(samples(100.0, std::f64::consts::FRAC_PI_2))
+            .build().unwrap();
+
+        parse_capture(&bytes, ParseOptions::default()).unwrap()
+    };
+    let files = [
+        (PathBuf::from("sweep/5kHz.bin"), capture(5000.0)),
+        (PathBuf::from("sweep/1kHz.bin"), capture(1000.0)),
+        (PathBuf::from("sweep/2.5kHz.bin"), capture(2500.0))
+    ];
+    let sweep = frequency_sweep_analysis(&files, 1, 2);
+
+    assert_eq!(sweep.points.iter().map(|point| point.frequency_hz).collect::<Vec<_>>(), [1000.0, 2500.0, 5000.0]);
+    for point in &sweep.points {
+        assert!((point.gain_db + 6.02).abs() < 0.1, "{:?}", point);
+        assert!((point.phase_deg + 90.0).abs() < 1.0, "{:?}", point);
+    }
+    assert!((frequency_sweep_analysis(&files, 2, 1).points[0].gain_db - 6.02).abs() < 0.1);
+    assert_eq!(frequency_from_filename(Path::new("sweep_1.5MHz.fnirsi")), Some(1.5e6));
+    assert_eq!(frequency_from_filename(Path::new("captures/500mhz")), Some(0.5));
+    assert_eq!(frequency_from_filename(Path::new("sweep_3.bin")), None);
+    assert_eq!(frequency_from_filename(Path::new("run2_10KHz_probe")), Some(1e4));
+    assert_eq!(frequency_from_filename(Path::new("sweep_2GHz.bin")), Some(2e9));
+}
+
+#[test]
+fn classifies_the_channels_on_request() {
+    let data = parse_capture(SINE, ParseOptions::builder().classify(true).build()).unwrap();
+    let dc = parse_capture(DC_LEVELS, ParseOptions::builder().classify(true).build()).unwrap();
+
+    assert_eq!(data.channel1.signal_type, Some(SignalType::Sine));
+    // One and a half periods of the square are too few to resolve its harmonics
+    assert_eq!(data.channel2.signal_type, Some(SignalType::Unknown));
+    assert_eq!(dc.channel1.signal_type, Some(SignalType::Dc));
+    assert_eq!(serde_json::to_value(&data.channel1).unwrap()["signal_type"], "sine");
+    assert!(parse_capture(SINE, ParseOptions::default()).unwrap().channel1.signal_type.is_none());
+}
+
+#[test]
+fn reads_the_samples_of_a_corrupt_capture() {
+    let mut bytes = SINE.to_vec();
+    // The first sample of channel 1 and the channel 2 offset beyond the 10 bits of the ADC
+    bytes[1000..1002].copy_from_slice(&2000u16.to_le_bytes());
+    bytes[86..88].copy_from_slice(&2000u16.to_le_bytes());
+    let data = parse_capture(&bytes, ParseOptions::default()).unwrap();
+    let codes = data.warnings.iter().map(|warning| warning.split(':').next().unwrap()).collect::<Vec<_>>();
+
+    // Both spikes also reach past the edges of the screen
+    assert_eq!(codes, ["suspicious_header", "samples_out_of_range", "clipping", "clipping"]);
+    assert_eq!(data.warnings[1], "samples_out_of_range: channel 1 has 1 samples outside of the ADC range 0..=1023");
+    match parse_capture(&bytes, ParseOptions::builder().strict(true).build()) {
+        Err(FnirsiError::InconsistentHeader(warning)) => assert_eq!(warning.field, "channel2_offset"),
+        other => panic!("Expected an inconsistent header, got {:?}", other.map(|_| ()))
+    }
+
+    bytes[86..88].copy_from_slice(&600u16.to_le_bytes());
+    match parse_capture(&bytes, ParseOptions::builder().strict(true).build()) {
+        Err(error @ FnirsiError::SamplesOutOfRange { channel: 1, count: 1 }) =>
+            assert_eq!(error.to_string(), "Channel 1 has 1 samples outside of the ADC range 0..=1023"),
+        other => panic!("Expected samples out of range, got {:?}", other.map(|_| ()))
+    }
+}